@@ -0,0 +1,591 @@
+//! Two-pass assembler for the mnemonics the trace and the disassembler print.
+//!
+//! ```text
+//! ; comments run to the end of the line
+//! SPEED = 2                   ; constants
+//! start:                      ; labels, optionally followed by an instruction
+//!     MOV V0, SPEED
+//!     MOV I, ball
+//!     DRW V0, V1, 4
+//!     JUMP start
+//! ball:
+//!     db %.##....., %####....  ; sprite literals: `#` is a lit pixel
+//!     db 0x0F, 240             ; bytes
+//!     dw 0x1234, ball          ; big-endian words
+//!     include "font.asm"       ; relative to the including file
+//! ```
+//!
+//! Numbers are decimal, `0x`/`$` hex or `0b`/`%` binary, and an operand may
+//! add and subtract any mix of numbers, labels and constants. The first byte
+//! is assembled at `APP_START`, so the output can be handed straight to
+//! `Chip8::load`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::chip8::APP_START;
+use crate::instruction::Instruction;
+use crate::platform::Platform;
+
+/// How deep `include`s may nest before we assume one includes itself.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// How many constants may refer to one another in a chain before we assume
+/// one refers to itself.
+const MAX_CONSTANT_DEPTH: usize = 64;
+
+/// Names that can't be labels or constants because they read as operands.
+const RESERVED: &[&str] = &["I", "DT", "ST", "K", "R", "LONG"];
+
+/// A problem with the source, pointing at where it was found. Lines and
+/// columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// The file the error is in, or `None` for source passed to `assemble`.
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let file = self.file.as_deref().unwrap_or("<input>");
+        write!(f, "{}:{}:{}: {}", file, self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// Assemble `source`. `include` paths are relative to the working directory.
+pub fn assemble(source: &str, platform: Platform) -> Result<Vec<u8>, AsmError> {
+    let mut assembler = Assembler::new(platform);
+    assembler.read(source, None, Path::new(""))?;
+    assembler.emit()
+}
+
+/// Assemble the file at `path`. `include` paths are relative to the file
+/// doing the including.
+pub fn assemble_file(path: &Path, platform: Platform) -> Result<Vec<u8>, AsmError> {
+    let source = fs::read_to_string(path).map_err(|err| AsmError {
+        file: Some(path.display().to_string()),
+        line: 0,
+        column: 0,
+        message: err.to_string(),
+    })?;
+
+    let mut assembler = Assembler::new(platform);
+    assembler.read(&source, Some(path.display().to_string()), path.parent().unwrap_or_else(|| Path::new("")))?;
+    assembler.emit()
+}
+
+/// Where a statement came from.
+#[derive(Debug, Clone)]
+struct Source {
+    file: Option<String>,
+    line: usize,
+}
+
+impl Source {
+    fn error<S: Into<String>>(&self, column: usize, message: S) -> AsmError {
+        AsmError { file: self.file.clone(), line: self.line, column, message: message.into() }
+    }
+}
+
+/// A piece of source text and the column it starts at.
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    column: usize,
+}
+
+enum Symbol {
+    Label(u16),
+    Constant { expression: Token, source: Source },
+}
+
+enum Item {
+    Instruction { mnemonic: String, operands: Vec<Token> },
+    Bytes(Vec<Token>),
+    Words(Vec<Token>),
+}
+
+struct Statement {
+    source: Source,
+    column: usize,
+    item: Item,
+}
+
+/// An operand once we know what kind of thing it names.
+enum Operand<'a> {
+    V(u8),
+    Range(u8, u8),
+    I,
+    AtI,
+    Dt,
+    St,
+    K,
+    R,
+    /// The address after `LONG`.
+    Long(Token),
+    Value(&'a Token),
+}
+
+struct Assembler {
+    platform: Platform,
+    /// Address the next statement will be assembled at.
+    address: usize,
+    symbols: HashMap<String, Symbol>,
+    statements: Vec<Statement>,
+    depth: usize,
+}
+
+impl Assembler {
+    fn new(platform: Platform) -> Assembler {
+        Assembler {
+            platform,
+            address: APP_START as usize,
+            symbols: HashMap::new(),
+            statements: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    /// First pass: define symbols and lay out statements.
+    fn read(&mut self, text: &str, file: Option<String>, directory: &Path) -> Result<(), AsmError> {
+        for (i, line) in text.lines().enumerate() {
+            let source = Source { file: file.clone(), line: i + 1 };
+            self.read_line(line, &source, directory)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_line(&mut self, line: &str, source: &Source, directory: &Path) -> Result<(), AsmError> {
+        let code = strip_comment(line);
+        let (mut rest, mut offset) = skip_space(code, 0);
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        let mut word = identifier(rest);
+        let after = rest[word.len()..].trim_start();
+        if !word.is_empty() && after.starts_with('=') {
+            self.define(word, source, offset + 1)?;
+            let start = code.len() - after.len() + 1;
+            let (expression, column) = skip_space(&code[start..], start);
+            if expression.is_empty() {
+                return Err(source.error(column + 1, format!("`{}` needs a value", word)));
+            }
+
+            let expression = Token { text: expression.trim_end().to_string(), column: column + 1 };
+            self.symbols.insert(word.to_string(), Symbol::Constant { expression, source: source.clone() });
+            return Ok(());
+        }
+
+        if !word.is_empty() && after.starts_with(':') {
+            self.define(word, source, offset + 1)?;
+            self.symbols.insert(word.to_string(), Symbol::Label(self.address as u16));
+
+            let start = code.len() - after.len() + 1;
+            let next = skip_space(&code[start..], start);
+            rest = next.0;
+            offset = next.1;
+            if rest.is_empty() {
+                return Ok(());
+            }
+            word = identifier(rest);
+        }
+
+        if word.is_empty() {
+            return Err(source.error(offset + 1, "expected an instruction or directive"));
+        }
+
+        let column = offset + 1;
+        let operands = split_operands(&rest[word.len()..], offset + word.len(), source)?;
+        let mnemonic = word.to_ascii_uppercase();
+
+        let (item, size) = match mnemonic.as_str() {
+            "INCLUDE" => return self.include(&operands, source, column, directory),
+            "DB" | "DW" if operands.is_empty() => {
+                return Err(source.error(column, format!("`{}` needs at least one value", word)));
+            }
+            "DB" => {
+                let size = operands.len();
+                (Item::Bytes(operands), size)
+            }
+            "DW" => {
+                let size = operands.len() * 2;
+                (Item::Words(operands), size)
+            }
+            _ => {
+                let long = mnemonic == "MOV" && operands.get(1).is_some_and(|operand| long_operand(&operand.text).is_some());
+                (Item::Instruction { mnemonic, operands }, if long { 4 } else { 2 })
+            }
+        };
+
+        if self.address + size > self.platform.memory_size() {
+            return Err(source.error(column, format!(
+                "program does not fit in memory ({} bytes from {:#X})",
+                self.platform.memory_size() - APP_START as usize,
+                APP_START
+            )));
+        }
+
+        self.statements.push(Statement { source: source.clone(), column, item });
+        self.address += size;
+        Ok(())
+    }
+
+    fn define(&self, name: &str, source: &Source, column: usize) -> Result<(), AsmError> {
+        if register(name).is_some() || RESERVED.iter().any(|reserved| reserved.eq_ignore_ascii_case(name)) {
+            return Err(source.error(column, format!("`{}` is reserved and can't be used as a name", name)));
+        }
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(source.error(column, format!("`{}` can't be used as a name", name)));
+        }
+        if self.symbols.contains_key(name) {
+            return Err(source.error(column, format!("`{}` is already defined", name)));
+        }
+
+        Ok(())
+    }
+
+    fn include(&mut self, operands: &[Token], source: &Source, column: usize, directory: &Path) -> Result<(), AsmError> {
+        let operand = match operands {
+            [operand] => operand,
+            _ => return Err(source.error(column, "`include` takes one quoted file name")),
+        };
+
+        let text = &operand.text;
+        if text.len() < 2 || !text.starts_with('"') || !text.ends_with('"') {
+            return Err(source.error(operand.column, "expected a quoted file name"));
+        }
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            return Err(source.error(operand.column, "includes nested too deeply; does a file include itself?"));
+        }
+
+        let path: PathBuf = directory.join(&text[1..text.len() - 1]);
+        let included = fs::read_to_string(&path)
+            .map_err(|err| source.error(operand.column, format!("cannot include `{}`: {}", path.display(), err)))?;
+
+        self.depth += 1;
+        let result = self.read(&included, Some(path.display().to_string()), path.parent().unwrap_or(directory));
+        self.depth -= 1;
+        result
+    }
+
+    /// Second pass: evaluate operands and produce the ROM.
+    fn emit(&self) -> Result<Vec<u8>, AsmError> {
+        let mut rom = Vec::new();
+
+        for statement in &self.statements {
+            let source = &statement.source;
+            match &statement.item {
+                Item::Bytes(values) => {
+                    for value in values {
+                        rom.push(self.value(value, source, -0x80, 0xFF, "a byte")? as u8);
+                    }
+                }
+                Item::Words(values) => {
+                    for value in values {
+                        let word = self.value(value, source, -0x8000, 0xFFFF, "a word")? as u16;
+                        rom.extend_from_slice(&[(word >> 8) as u8, word as u8]);
+                    }
+                }
+                Item::Instruction { mnemonic, operands } => {
+                    let instruction = self.instruction(mnemonic, operands, source, statement.column)?;
+                    let bytes = instruction.encode();
+                    if Instruction::decode(&bytes, self.platform) != Some(instruction) {
+                        return Err(source.error(statement.column, format!(
+                            "`{}` is not available on the {} platform",
+                            instruction, self.platform
+                        )));
+                    }
+
+                    rom.extend_from_slice(&bytes);
+                }
+            }
+        }
+
+        Ok(rom)
+    }
+
+    fn instruction(&self, mnemonic: &str, operands: &[Token], source: &Source, column: usize) -> Result<Instruction, AsmError> {
+        use self::Instruction::*;
+        use self::Operand::*;
+
+        let operands: Vec<Operand> = operands.iter().map(classify).collect();
+        let byte = |token: &Token| self.value(token, source, -0x80, 0xFF, "a byte").map(|value| value as u8);
+        let nibble = |token: &Token| self.value(token, source, 0, 0xF, "a nibble").map(|value| value as u8);
+        let address = |token: &Token| self.value(token, source, 0, 0xFFF, "12 bits").map(|value| value as u16);
+
+        let instruction = match (mnemonic, operands.as_slice()) {
+            ("CLS", []) => Cls,
+            ("RET", []) => Ret,
+            ("SCD", [Value(n)]) => ScrollDown(nibble(n)?),
+            ("SCU", [Value(n)]) => ScrollUp(nibble(n)?),
+            ("SCR", []) => ScrollRight,
+            ("SCL", []) => ScrollLeft,
+            ("EXIT", []) => Exit,
+            ("LOW", []) => Low,
+            ("HIGH", []) => High,
+            ("JUMP", [Value(addr)]) => Jump(address(addr)?),
+            ("CALL", [Value(addr)]) => Call(address(addr)?),
+            ("JE", [V(x), V(y)]) => SkipEq(*x, *y),
+            ("JE", [V(x), Value(nn)]) => SkipEqByte(*x, byte(nn)?),
+            ("JNE", [V(x), V(y)]) => SkipNe(*x, *y),
+            ("JNE", [V(x), Value(nn)]) => SkipNeByte(*x, byte(nn)?),
+            ("MOV", [AtI, Range(x, y)]) => StoreRange(*x, *y),
+            ("MOV", [Range(x, y), AtI]) => LoadRange(*x, *y),
+            ("MOV", [V(x), V(y)]) => Move(*x, *y),
+            ("MOV", [V(x), Value(nn)]) => LoadByte(*x, byte(nn)?),
+            ("MOV", [I, Long(addr)]) => {
+                LoadLongIndex(self.value(addr, source, 0, 0xFFFF, "16 bits")? as u16)
+            }
+            ("MOV", [I, Value(addr)]) => LoadIndex(address(addr)?),
+            ("MOV", [V(x), Dt]) => GetDelay(*x),
+            ("MOV", [V(x), K]) => WaitKey(*x),
+            ("MOV", [Dt, V(x)]) => SetDelay(*x),
+            ("MOV", [St, V(x)]) => SetSound(*x),
+            ("MOV", [AtI, V(x)]) => Store(*x),
+            ("MOV", [V(x), AtI]) => Load(*x),
+            ("MOV", [R, V(x)]) => StoreFlags(*x),
+            ("MOV", [V(x), R]) => LoadFlags(*x),
+            ("ADD", [V(x), Value(nn)]) => AddByte(*x, byte(nn)?),
+            ("ADD", [I, V(x)]) => AddIndex(*x),
+            ("OR", [V(x), V(y)]) => Or(*x, *y),
+            ("AND", [V(x), V(y)]) => And(*x, *y),
+            ("XOR", [V(x), V(y)]) => Xor(*x, *y),
+            ("ADC", [V(x), V(y)]) => Add(*x, *y),
+            ("SUB", [V(x), V(y)]) => Sub(*x, *y),
+            ("SUBN", [V(x), V(y)]) => SubN(*x, *y),
+            ("SHR", [V(x)]) => Shr(*x, *x),
+            ("SHR", [V(x), V(y)]) => Shr(*x, *y),
+            ("SHL", [V(x)]) => Shl(*x, *x),
+            ("SHL", [V(x), V(y)]) => Shl(*x, *y),
+            ("JMP", [Value(addr)]) => JumpOffset(address(addr)?),
+            ("RND", [V(x), Value(nn)]) => Random(*x, byte(nn)?),
+            ("DRW", [V(x), V(y), Value(n)]) => Draw(*x, *y, nibble(n)?),
+            ("SKP", [V(x)]) => SkipKey(*x),
+            ("SKNP", [V(x)]) => SkipNoKey(*x),
+            ("FONT", [V(x)]) => Font(*x),
+            ("HFONT", [V(x)]) => BigFont(*x),
+            ("BCD", [V(x)]) => Bcd(*x),
+            ("PLANE", [Value(n)]) => Plane(self.value(n, source, 0, 3, "a plane mask")? as u8),
+            ("AUDIO", []) => Audio,
+            ("PITCH", [V(x)]) => Pitch(*x),
+            _ if MNEMONICS.contains(&mnemonic) => {
+                return Err(source.error(column, format!("bad operands for `{}`", mnemonic)));
+            }
+            _ => return Err(source.error(column, format!("unknown instruction `{}`", mnemonic))),
+        };
+
+        Ok(instruction)
+    }
+
+    /// Evaluate `token` and check it lies in `min..=max`.
+    fn value(&self, token: &Token, source: &Source, min: i64, max: i64, what: &str) -> Result<i64, AsmError> {
+        let value = self.evaluate(token, source, 0)?;
+        if value < min || value > max {
+            return Err(source.error(token.column, format!("{} does not fit in {}", value, what)));
+        }
+
+        Ok(value)
+    }
+
+    /// Sum up the terms of an expression like `table + 2 - OFFSET`.
+    fn evaluate(&self, token: &Token, source: &Source, depth: usize) -> Result<i64, AsmError> {
+        let text = &token.text;
+        let mut total: i64 = 0;
+        let mut sign = 1;
+        let mut position = 0;
+        let mut expect_term = true;
+
+        while position < text.len() {
+            let rest = &text[position..];
+            let c = rest.chars().next().unwrap_or(' ');
+            let column = token.column + position;
+
+            if c.is_whitespace() {
+                position += c.len_utf8();
+            } else if c == '+' || c == '-' {
+                if expect_term && c == '+' {
+                    return Err(source.error(column, "expected a value"));
+                }
+                if c == '-' {
+                    sign = -sign;
+                }
+                expect_term = true;
+                position += 1;
+            } else if expect_term {
+                let length = rest.find(|c: char| !is_term_char(c)).unwrap_or(rest.len());
+                if length == 0 {
+                    return Err(source.error(column, format!("unexpected `{}`", c)));
+                }
+
+                let term = Token { text: rest[..length].to_string(), column };
+                total = total.wrapping_add(sign * self.term(&term, source, depth)?);
+                sign = 1;
+                expect_term = false;
+                position += length;
+            } else {
+                return Err(source.error(column, format!("expected `+` or `-`, found `{}`", c)));
+            }
+        }
+
+        if expect_term {
+            return Err(source.error(token.column + text.len(), "expected a value"));
+        }
+
+        Ok(total)
+    }
+
+    fn term(&self, term: &Token, source: &Source, depth: usize) -> Result<i64, AsmError> {
+        let text = term.text.as_str();
+        if text.starts_with(|c: char| c.is_ascii_digit() || c == '$' || c == '%') {
+            return number(text).ok_or_else(|| source.error(term.column, format!("bad number `{}`", text)));
+        }
+
+        match self.symbols.get(text) {
+            Some(Symbol::Label(address)) => Ok(*address as i64),
+            Some(Symbol::Constant { expression, source: defined }) => {
+                if depth >= MAX_CONSTANT_DEPTH {
+                    return Err(source.error(term.column, format!("`{}` is defined in terms of itself", text)));
+                }
+                self.evaluate(expression, defined, depth + 1)
+            }
+            None => Err(source.error(term.column, format!("undefined name `{}`", text))),
+        }
+    }
+}
+
+/// Every mnemonic `Instruction`'s `Display` prints.
+const MNEMONICS: &[&str] = &[
+    "CLS", "RET", "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JUMP", "CALL", "JE", "JNE", "MOV", "ADD", "OR",
+    "AND", "XOR", "ADC", "SUB", "SUBN", "SHR", "SHL", "JMP", "RND", "DRW", "SKP", "SKNP", "FONT", "HFONT", "BCD",
+    "PLANE", "AUDIO", "PITCH",
+];
+
+fn classify(token: &Token) -> Operand<'_> {
+    if let Some(x) = register(&token.text) {
+        return Operand::V(x);
+    }
+    if let Some(address) = long_operand(&token.text) {
+        let column = token.column + token.text.len() - address.len();
+        return Operand::Long(Token { text: address.to_string(), column });
+    }
+
+    match token.text.to_ascii_uppercase().as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::AtI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "R" => Operand::R,
+        text => {
+            let mut range = text.splitn(2, '-').map(str::trim);
+            match (range.next().and_then(register), range.next().and_then(register)) {
+                (Some(x), Some(y)) => Operand::Range(x, y),
+                _ => Operand::Value(token),
+            }
+        }
+    }
+}
+
+/// `V0` to `VF`, in either case.
+fn register(text: &str) -> Option<u8> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('V'), Some(digit), None) | (Some('v'), Some(digit), None) => digit.to_digit(16).map(|x| x as u8),
+        _ => None,
+    }
+}
+
+/// The address part of a `LONG addr` operand.
+fn long_operand(text: &str) -> Option<&str> {
+    let keyword = text.get(..4)?;
+    let rest = &text[4..];
+    if keyword.eq_ignore_ascii_case("LONG") && rest.starts_with(char::is_whitespace) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+fn number(text: &str) -> Option<i64> {
+    let binary = |digits: &str| {
+        let digits: String = digits.chars().map(|c| match c {
+            '.' => '0',
+            '#' => '1',
+            c => c,
+        }).collect();
+        i64::from_str_radix(&digits, 2).ok()
+    };
+
+    if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).or_else(|| text.strip_prefix('$')) {
+        i64::from_str_radix(digits, 16).ok()
+    } else if let Some(digits) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")).or_else(|| text.strip_prefix('%')) {
+        binary(digits)
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn is_term_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '#' || c == '$' || c == '%'
+}
+
+/// The identifier `text` starts with, possibly empty.
+fn identifier(text: &str) -> &str {
+    let length = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+        .unwrap_or(text.len());
+    &text[..length]
+}
+
+/// `text` without leading whitespace, and the offset it now starts at.
+fn skip_space(text: &str, offset: usize) -> (&str, usize) {
+    let trimmed = text.trim_start();
+    (trimmed, offset + text.len() - trimmed.len())
+}
+
+/// Everything before a `;` that isn't inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// Split the comma-separated operands in `text`, which starts at `offset`
+/// in its line.
+fn split_operands(text: &str, offset: usize, source: &Source) -> Result<Vec<Token>, AsmError> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut operands = Vec::new();
+    let mut start = 0;
+    for piece in text.split(',') {
+        let (trimmed, column) = skip_space(piece, offset + start);
+        let trimmed = trimmed.trim_end();
+        if trimmed.is_empty() {
+            return Err(source.error(column + 1, "missing operand"));
+        }
+
+        operands.push(Token { text: trimmed.to_string(), column: column + 1 });
+        start += piece.len() + 1;
+    }
+
+    Ok(operands)
+}
@@ -0,0 +1,185 @@
+//! Tone generation for the beeper, shared by every audio backend, plus a
+//! backend that captures the beeper to a WAV file for headless runs.
+
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::clock::FRAME_RATE;
+use crate::frontend::Audio;
+
+pub const SAMPLE_RATE: u32 = 44100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" => Ok(Waveform::Sawtooth),
+            "sine" => Ok(Waveform::Sine),
+            _ => Err(format!("unknown waveform `{}` (expected square, triangle, sawtooth or sine)", s)),
+        }
+    }
+}
+
+/// What the beeper sounds like.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    /// Pitch in Hz.
+    pub frequency: f32,
+    /// Amplitude between 0.0 and 1.0.
+    pub volume: f32,
+    pub waveform: Waveform,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Tone {
+            frequency: 440.0,
+            volume: 0.25,
+            waveform: Waveform::Square,
+        }
+    }
+}
+
+/// Produces samples of a `Tone`, or of an XO-CHIP pattern once one is set,
+/// gated on and off by the sound timer.
+pub struct Oscillator {
+    pub tone: Tone,
+    pub playing: bool,
+    sample_rate: u32,
+    phase: f32,
+    pattern: Option<[u8; 16]>,
+    pattern_rate: f32,
+}
+
+impl Oscillator {
+    pub fn new(tone: Tone, sample_rate: u32) -> Oscillator {
+        Oscillator {
+            tone,
+            playing: false,
+            sample_rate,
+            phase: 0.0,
+            pattern: None,
+            pattern_rate: 4000.0,
+        }
+    }
+
+    /// Play `pattern` instead of the tone. XO-CHIP plays the 128 bits at
+    /// 4000 * 2^((pitch - 64) / 48) bits per second.
+    pub fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        self.pattern = Some(*pattern);
+        self.pattern_rate = 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        if !self.playing {
+            return 0.0;
+        }
+
+        if let Some(pattern) = &self.pattern {
+            let bit = (self.phase * 128.0) as usize % 128;
+            let value = if pattern[bit / 8] >> (7 - bit % 8) & 1 == 1 { 1.0 } else { -1.0 };
+            self.phase = (self.phase + self.pattern_rate / 128.0 / self.sample_rate as f32) % 1.0;
+            return value * self.tone.volume;
+        }
+
+        let value = match self.tone.waveform {
+            Waveform::Square => if self.phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * self.phase - 1.0,
+            Waveform::Sine => (2.0 * PI * self.phase).sin(),
+        };
+
+        self.phase = (self.phase + self.tone.frequency / self.sample_rate as f32) % 1.0;
+        value * self.tone.volume
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.next_sample();
+        }
+    }
+}
+
+/// Audio backend that renders one frame's worth of samples per update into a
+/// 16-bit mono WAV file. The header sizes are filled in when it is dropped.
+pub struct WavRecorder {
+    writer: BufWriter<File>,
+    oscillator: Oscillator,
+    samples: u32,
+}
+
+impl WavRecorder {
+    pub fn create<P: AsRef<Path>>(path: P, tone: Tone) -> io::Result<WavRecorder> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_wav_header(&mut writer, 0)?;
+
+        Ok(WavRecorder {
+            writer,
+            oscillator: Oscillator::new(tone, SAMPLE_RATE),
+            samples: 0,
+        })
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        write_wav_header(&mut self.writer, self.samples)?;
+        self.writer.flush()
+    }
+}
+
+impl Drop for WavRecorder {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+impl Audio for WavRecorder {
+    fn update(&mut self, beeping: bool) {
+        self.oscillator.playing = beeping;
+
+        for _ in 0..SAMPLE_RATE / FRAME_RATE {
+            let sample = (self.oscillator.next_sample() * i16::MAX as f32) as i16;
+            if self.writer.write_all(&sample.to_le_bytes()).is_err() {
+                return;
+            }
+
+            self.samples += 1;
+        }
+    }
+
+    fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        self.oscillator.set_pattern(pattern, pitch);
+    }
+}
+
+fn write_wav_header<W: Write>(writer: &mut W, samples: u32) -> io::Result<()> {
+    let data_size = samples * 2;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // mono
+    writer.write_all(&SAMPLE_RATE.to_le_bytes())?;
+    writer.write_all(&(SAMPLE_RATE * 2).to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())
+}
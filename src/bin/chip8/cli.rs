@@ -0,0 +1,406 @@
+//! Command-line parsing for the `chip8` binary.
+
+use chip8::audio::{Tone, Waveform};
+use chip8::clock::DEFAULT_IPS;
+use chip8::frontend::DEFAULT_PALETTE;
+use chip8::quirks::{FLAGS, PRESETS};
+use chip8::runner::{Sections, Until};
+use chip8::trace::DEFAULT_CAPACITY;
+use chip8::{Platform, Quirks};
+
+pub const USAGE: &str = "usage: chip8 [OPTIONS] ROM
+       chip8 disasm [--platform NAME] ROM
+       chip8 asm [--platform NAME] [-o OUT] SOURCE
+       chip8 run [--frames N] [--until-...] [--expect EXPR=VALUE] ROM
+
+options:
+    --platform NAME     instruction set: chip8, schip or xochip (default chip8)
+    --quirks SPEC       quirks preset and overrides, e.g. vip or schip,shift_vy=on
+    --ips N             instructions per second (default 700)
+    --scale N           window pixels per CHIP-8 pixel (default 10)
+    --palette COLORS    up to four RRGGBB colours, e.g. 000000,FFFFFF
+    --keymap FILE       host key bindings (default ~/.config/chip8/keymap if
+                        it exists, otherwise the COSMAC layout on 1-4/Q-R/A-F/Z-V)
+    --beep HZ           beeper frequency, 20 to 20000 (default 440)
+    --volume N          beeper volume between 0 and 1 (default 0.25)
+    --waveform NAME     square, triangle, sawtooth or sine (default square)
+    --headless          run without a window
+    --wav FILE          with --headless, record the beeper to a WAV file
+    --trace             print every instruction to stderr
+    --trace-size N      instructions kept for crash dumps, 0 to disable (default 256)
+    --rewind SECONDS    history kept for stepping back, 0 to disable (default 10,
+                        at most 120)
+    --debug             start paused with debugger commands read from the terminal
+    --break ADDR        stop in the debugger at a hex address (implies --debug)
+    --seed N            seed the random number generator for CXNN (random
+                        by default; --trace and crash dumps show the seed)
+    --load-state FILE   start from a save state instead of a fresh machine
+    --record FILE       record the keypad to a movie file
+    --replay FILE       replay a movie; its platform, quirks, seed and speed
+                        replace the options, and --headless stops at its end
+    -o, --output OUT    asm: where to write the ROM (default SOURCE with .ch8)
+    -h, --help          show this message
+
+run executes the ROM without a window and dumps the machine when it stops.
+it takes --platform, --quirks, --ips and --seed (default 0) as well as:
+    --frames N          stop after N frames (default 3600 with --until)
+    --until-pc ADDR     stop when PC reaches ADDR
+    --until-mem ADDR=N  stop when the byte at ADDR holds N (both hex)
+    --until-self-jump   stop at a 1NNN jump to itself
+    --expect EXPR=N     fail unless EXPR (as in the debugger, e.g. V3 or [300])
+                        equals hex N at the end; may be repeated
+    --format FORMAT     text or json (default text)
+    --dump SECTIONS     any of registers,screen,memory (default all)
+it exits with 1 if the machine faults, an expectation fails, or the frame limit
+runs out before an --until condition is met.";
+
+/// Longest `--rewind` in seconds. At ten snapshots a second, two minutes of
+/// XO-CHIP's 64K of memory is already about 90 MB.
+const MAX_REWIND: u32 = 120;
+
+pub struct Options {
+    pub rom: String,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub ips: u32,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub scale: u32,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub palette: [u32; 4],
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    pub keymap: Option<String>,
+    pub tone: Tone,
+    pub headless: bool,
+    pub wav: Option<String>,
+    pub trace: bool,
+    pub trace_size: usize,
+    pub rewind: u32,
+    pub debug: bool,
+    pub breakpoints: Vec<u16>,
+    pub seed: Option<u64>,
+    pub load_state: Option<String>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+}
+
+/// Options for `chip8 run`.
+pub struct Batch {
+    pub rom: String,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub ips: u32,
+    pub seed: u64,
+    pub frames: Option<usize>,
+    pub until: Vec<Until>,
+    /// `(expression, value)` pairs checked at the end.
+    pub expect: Vec<(String, i64)>,
+    pub json: bool,
+    pub sections: Sections,
+}
+
+/// What the command line asked for.
+pub enum Action {
+    Run(Options),
+    /// Print a listing of the ROM instead of running it.
+    Disasm { rom: String, platform: Platform },
+    /// Assemble a source file into a ROM.
+    Asm { source: String, output: Option<String>, platform: Platform },
+    /// Run without a window until a condition and dump the machine.
+    Batch(Batch),
+    Help,
+}
+
+pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Action, String> {
+    let mut args = args.peekable();
+    match args.peek().map(String::as_str) {
+        Some("disasm") => {
+            args.next();
+            parse_disasm(args)
+        }
+        Some("asm") => {
+            args.next();
+            parse_asm(args)
+        }
+        Some("run") => {
+            args.next();
+            parse_batch(args)
+        }
+        _ => parse_run(args),
+    }
+}
+
+fn parse_disasm<I: Iterator<Item = String>>(mut args: I) -> Result<Action, String> {
+    let mut rom = None;
+    let mut platform = Platform::Chip8;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Action::Help),
+            "--platform" => platform = value(&mut args, &arg)?.parse()?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ => {
+                if rom.is_some() {
+                    return Err(format!("unexpected argument `{}`", arg));
+                }
+                rom = Some(arg);
+            }
+        }
+    }
+
+    Ok(Action::Disasm { rom: rom.ok_or("no ROM given")?, platform })
+}
+
+fn parse_asm<I: Iterator<Item = String>>(mut args: I) -> Result<Action, String> {
+    let mut source = None;
+    let mut output = None;
+    let mut platform = Platform::Chip8;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Action::Help),
+            "--platform" => platform = value(&mut args, &arg)?.parse()?,
+            "-o" | "--output" => output = Some(value(&mut args, &arg)?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ => {
+                if source.is_some() {
+                    return Err(format!("unexpected argument `{}`", arg));
+                }
+                source = Some(arg);
+            }
+        }
+    }
+
+    Ok(Action::Asm { source: source.ok_or("no source file given")?, output, platform })
+}
+
+fn parse_batch<I: Iterator<Item = String>>(mut args: I) -> Result<Action, String> {
+    let mut rom = None;
+    let mut platform = Platform::Chip8;
+    let mut quirks = None;
+    let mut ips = DEFAULT_IPS;
+    let mut seed = 0;
+    let mut frames = None;
+    let mut until = Vec::new();
+    let mut expect = Vec::new();
+    let mut json = false;
+    let mut sections = Sections::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Action::Help),
+            "--platform" => platform = value(&mut args, &arg)?.parse()?,
+            "--quirks" => quirks = Some(value(&mut args, &arg)?.parse::<Quirks>()?),
+            "--ips" => ips = number(&value(&mut args, &arg)?, &arg)?,
+            "--seed" => seed = parse_seed(&value(&mut args, &arg)?)?,
+            "--frames" => frames = Some(number(&value(&mut args, &arg)?, &arg)?),
+            "--until-pc" => until.push(Until::Pc(parse_address(&value(&mut args, &arg)?)?)),
+            "--until-mem" => {
+                let (address, value) = split_assignment(&value(&mut args, &arg)?, &arg)?;
+                let value = u8::from_str_radix(&value, 16).map_err(|_| format!("bad byte `{}` for {}", value, arg))?;
+                until.push(Until::Memory { address: parse_address(&address)?, value });
+            }
+            "--until-self-jump" => until.push(Until::SelfJump),
+            "--expect" => {
+                let (expression, value) = split_assignment(&value(&mut args, &arg)?, &arg)?;
+                let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+                let value = i64::from_str_radix(digits, 16).map_err(|_| format!("bad value `{}` for {}", value, arg))?;
+                expect.push((expression, value));
+            }
+            "--format" => {
+                json = match value(&mut args, &arg)?.as_str() {
+                    "text" => false,
+                    "json" => true,
+                    other => return Err(format!("unknown format `{}` (expected text or json)", other)),
+                }
+            }
+            "--dump" => sections = value(&mut args, &arg)?.parse()?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ => {
+                if rom.is_some() {
+                    return Err(format!("unexpected argument `{}`", arg));
+                }
+                rom = Some(arg);
+            }
+        }
+    }
+
+    if ips == 0 {
+        return Err("--ips must be at least 1".to_string());
+    }
+    if frames.is_none() && until.is_empty() {
+        return Err("run needs --frames or an --until condition".to_string());
+    }
+
+    Ok(Action::Batch(Batch {
+        rom: rom.ok_or("no ROM given")?,
+        platform,
+        quirks: quirks.unwrap_or_else(|| platform.default_quirks()),
+        ips,
+        seed,
+        frames,
+        until,
+        expect,
+        json,
+        sections,
+    }))
+}
+
+/// `LEFT=RIGHT`, both sides trimmed.
+fn split_assignment(value: &str, option: &str) -> Result<(String, String), String> {
+    match value.find('=') {
+        Some(eq) => Ok((value[..eq].trim().to_string(), value[eq + 1..].trim().to_string())),
+        None => Err(format!("{} takes LEFT=RIGHT, got `{}`", option, value)),
+    }
+}
+
+fn parse_run<I: Iterator<Item = String>>(mut args: I) -> Result<Action, String> {
+    let mut rom = None;
+    let mut platform = Platform::Chip8;
+    let mut quirks = None;
+    let mut ips = DEFAULT_IPS;
+    let mut scale = 10;
+    let mut palette = DEFAULT_PALETTE;
+    let mut keymap = None;
+    let mut tone = Tone::default();
+    let mut headless = false;
+    let mut wav = None;
+    let mut trace = false;
+    let mut trace_size = DEFAULT_CAPACITY;
+    let mut rewind = 10;
+    let mut debug = false;
+    let mut breakpoints = Vec::new();
+    let mut seed = None;
+    let mut load_state = None;
+    let mut record = None;
+    let mut replay = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Action::Help),
+            "--platform" => platform = value(&mut args, &arg)?.parse()?,
+            "--quirks" => {
+                let spec = value(&mut args, &arg)?;
+                quirks = Some(spec.parse::<Quirks>().map_err(|err| {
+                    format!("{}\npresets: {}\nflags: {}", err, PRESETS.join(", "), FLAGS.join(", "))
+                })?);
+            }
+            "--ips" => ips = number(&value(&mut args, &arg)?, &arg)?,
+            "--scale" => scale = number(&value(&mut args, &arg)?, &arg)?,
+            "--palette" => palette = parse_palette(&value(&mut args, &arg)?)?,
+            "--keymap" => keymap = Some(value(&mut args, &arg)?),
+            "--beep" => tone.frequency = number(&value(&mut args, &arg)?, &arg)?,
+            "--volume" => tone.volume = number(&value(&mut args, &arg)?, &arg)?,
+            "--waveform" => tone.waveform = value(&mut args, &arg)?.parse::<Waveform>()?,
+            "--headless" => headless = true,
+            "--wav" => wav = Some(value(&mut args, &arg)?),
+            "--trace" => trace = true,
+            "--trace-size" => trace_size = number(&value(&mut args, &arg)?, &arg)?,
+            "--rewind" => rewind = number(&value(&mut args, &arg)?, &arg)?,
+            "--debug" => debug = true,
+            "--break" => breakpoints.push(parse_address(&value(&mut args, &arg)?)?),
+            "--seed" => seed = Some(parse_seed(&value(&mut args, &arg)?)?),
+            "--load-state" => load_state = Some(value(&mut args, &arg)?),
+            "--record" => record = Some(value(&mut args, &arg)?),
+            "--replay" => replay = Some(value(&mut args, &arg)?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+            _ => {
+                if rom.is_some() {
+                    return Err(format!("unexpected argument `{}`", arg));
+                }
+                rom = Some(arg);
+            }
+        }
+    }
+
+    if ips == 0 {
+        return Err("--ips must be at least 1".to_string());
+    }
+    if rewind > MAX_REWIND {
+        return Err(format!("--rewind can be at most {} seconds", MAX_REWIND));
+    }
+    if scale == 0 {
+        return Err("--scale must be at least 1".to_string());
+    }
+    if !(20.0..=20000.0).contains(&tone.frequency) {
+        return Err("--beep must be between 20 and 20000 Hz".to_string());
+    }
+    if !(0.0..=1.0).contains(&tone.volume) {
+        return Err("--volume must be between 0 and 1".to_string());
+    }
+    if wav.is_some() && !headless {
+        return Err("--wav only works with --headless".to_string());
+    }
+    if record.is_some() && replay.is_some() {
+        return Err("--record and --replay can't be used together".to_string());
+    }
+    if load_state.is_some() && (record.is_some() || replay.is_some()) {
+        return Err("movies start from a fresh machine, not --load-state".to_string());
+    }
+    if (record.is_some() || replay.is_some()) && (debug || !breakpoints.is_empty()) {
+        return Err("movies can't be used with --debug or --break, which change where frames start".to_string());
+    }
+
+    Ok(Action::Run(Options {
+        rom: rom.ok_or("no ROM given")?,
+        platform,
+        quirks: quirks.unwrap_or_else(|| platform.default_quirks()),
+        ips,
+        scale,
+        palette,
+        keymap,
+        tone,
+        headless,
+        wav,
+        trace,
+        trace_size,
+        rewind,
+        debug,
+        breakpoints,
+        seed,
+        load_state,
+        record,
+        replay,
+    }))
+}
+
+fn value<I: Iterator<Item = String>>(args: &mut I, option: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} needs a value", option))
+}
+
+fn number<T: std::str::FromStr>(value: &str, option: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("bad value `{}` for {}", value, option))
+}
+
+/// Addresses are hex, with or without a `0x` prefix.
+pub fn parse_address(value: &str) -> Result<u16, String> {
+    let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("bad address `{}` (expected hex, e.g. 2A0)", value))
+}
+
+fn parse_seed(value: &str) -> Result<u64, String> {
+    let parsed = if value.starts_with("0x") || value.starts_with("0X") {
+        u64::from_str_radix(&value[2..], 16)
+    } else {
+        value.parse()
+    };
+
+    parsed.map_err(|_| format!("bad seed `{}`", value))
+}
+
+fn parse_palette(value: &str) -> Result<[u32; 4], String> {
+    let mut palette = DEFAULT_PALETTE;
+    let colors: Vec<&str> = value.split(',').collect();
+    if colors.len() > palette.len() {
+        return Err(format!("--palette takes at most {} colours", palette.len()));
+    }
+
+    for (slot, color) in palette.iter_mut().zip(colors) {
+        let digits = color.trim().trim_start_matches('#');
+        if digits.len() != 6 {
+            return Err(format!("bad colour `{}` (expected RRGGBB)", color));
+        }
+        *slot = u32::from_str_radix(digits, 16).map_err(|_| format!("bad colour `{}` (expected RRGGBB)", color))?;
+    }
+
+    Ok(palette)
+}
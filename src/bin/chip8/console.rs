@@ -0,0 +1,53 @@
+//! Debugger commands typed into the terminal. Lines are read on their own
+//! thread so the window keeps running while nobody is typing.
+
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+pub struct Console {
+    lines: Receiver<String>,
+    closed: bool,
+}
+
+impl Console {
+    pub fn spawn() -> Console {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Console { lines, closed: false }
+    }
+
+    /// The next line typed, if there is one yet.
+    pub fn line(&mut self) -> Option<String> {
+        match self.lines.try_recv() {
+            Ok(line) => Some(line),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.closed = true;
+                None
+            }
+        }
+    }
+
+    /// Whether stdin has reached end of file, so no more commands will come.
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn prompt(&self) {
+        print!("(chip8) ");
+        let _ = io::stdout().flush();
+    }
+}
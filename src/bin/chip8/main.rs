@@ -0,0 +1,394 @@
+mod cli;
+mod console;
+#[cfg(feature = "sdl")]
+mod sdl;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use chip8::asm::assemble_file;
+use chip8::audio::WavRecorder;
+use chip8::clock::{instructions_per_frame, FrameClock};
+use chip8::debugger::{evaluate, Debugger, Outcome};
+use chip8::disasm::disassemble;
+use chip8::frontend::{Audio, Command, Display, Input, NullAudio, NullDisplay, NullInput};
+use chip8::movie::{Movie, Replay};
+use chip8::rewind::Rewind;
+use chip8::runner::{self, Finish};
+use chip8::savestate;
+use chip8::trace::Trace;
+use chip8::{Chip8, Machine, Platform};
+
+use crate::cli::{Action, Batch, Options, USAGE};
+use crate::console::Console;
+
+type Frontends = (Box<dyn Display>, Box<dyn Input>, Box<dyn Audio>);
+
+fn main() {
+    let mut options = match cli::parse(env::args().skip(1)) {
+        Ok(Action::Run(options)) => options,
+        Ok(Action::Disasm { rom, platform }) => {
+            disasm(&rom, platform);
+            return;
+        }
+        Ok(Action::Asm { source, output, platform }) => {
+            asm(&source, output, platform);
+            return;
+        }
+        Ok(Action::Batch(batch)) => process::exit(run_batch(&batch)),
+        Ok(Action::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("chip8: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    // A replay only reproduces the run under the settings it was recorded with.
+    let replay = options.replay.clone().map(|path| {
+        let movie = read_movie(&path, &options.rom);
+        options.platform = movie.platform;
+        options.quirks = movie.quirks;
+        options.seed = Some(movie.seed);
+        options.ips = movie.ips;
+        Replay::new(movie)
+    });
+
+    let mut chip8 = Chip8::with_platform(options.platform, options.quirks);
+    if let Err(err) = chip8.load(&options.rom) {
+        eprintln!("chip8: cannot load `{}`: {}", options.rom, err);
+        process::exit(1);
+    }
+
+    if let Some(seed) = options.seed {
+        chip8.seed(seed);
+    }
+    if options.trace {
+        eprintln!("seed {}", chip8.rng.seed);
+    }
+    if let Some(path) = &options.load_state {
+        if let Err(err) = savestate::read(&mut chip8, Path::new(path)) {
+            eprintln!("chip8: cannot load state `{}`: {}", path, err);
+            process::exit(1);
+        }
+    }
+    chip8.breakpoints = options.breakpoints.clone();
+
+    let frontends = if options.headless { headless(&options) } else { window(&options) };
+    let (display, input, audio) = frontends.unwrap_or_else(|err| {
+        eprintln!("chip8: {}", err);
+        process::exit(1);
+    });
+
+    let mut machine = Machine::new(chip8, display, input, audio);
+    machine.set_ips(options.ips);
+
+    // --trace drains the buffer every frame, so it must hold a whole frame.
+    let trace_size = if options.trace {
+        options.trace_size.max(machine.instructions_per_frame)
+    } else {
+        options.trace_size
+    };
+    machine.chip8.trace = if trace_size > 0 { Some(Trace::new(trace_size)) } else { None };
+
+    machine.replay = replay;
+    if options.record.is_some() {
+        let rom = fs::read(&options.rom).unwrap_or_else(|err| {
+            eprintln!("chip8: cannot read `{}`: {}", options.rom, err);
+            process::exit(1);
+        });
+        machine.recording = Some(Movie::new(&rom, &machine.chip8, options.ips));
+    }
+
+    // Ten snapshots a second keeps replays short and XO-CHIP's 64K of memory
+    // affordable. Movies can't follow the machine backwards.
+    if options.rewind > 0 && options.record.is_none() && options.replay.is_none() {
+        let interval = machine.instructions_per_frame * 6;
+        machine.chip8.rewind = Some(Rewind::new(options.rewind as usize * 10, interval));
+    }
+
+    let code = run(&mut machine, &options);
+    if let (Some(path), Some(movie)) = (&options.record, machine.recording.take()) {
+        match movie.write(Path::new(path)) {
+            Ok(()) => eprintln!("chip8: recorded {} frames to `{}`", movie.frames.len(), path),
+            Err(err) => eprintln!("chip8: cannot write `{}`: {}", path, err),
+        }
+    }
+
+    // Dropping the machine flushes recorders such as the WAV writer.
+    drop(machine);
+    process::exit(code);
+}
+
+/// `chip8 run`: run headless until a condition, print the report and check
+/// the expectations. Returns the exit code.
+fn run_batch(batch: &Batch) -> i32 {
+    let mut chip8 = Chip8::with_platform(batch.platform, batch.quirks);
+    if let Err(err) = chip8.load(&batch.rom) {
+        eprintln!("chip8: cannot load `{}`: {}", batch.rom, err);
+        return 1;
+    }
+    chip8.seed(batch.seed);
+    chip8.trace = None;
+
+    let report = match runner::run(&mut chip8, instructions_per_frame(batch.ips), batch.frames, &batch.until) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("chip8: {}", err);
+            return 1;
+        }
+    };
+
+    if batch.json {
+        println!("{}", report.json(&chip8, batch.sections));
+    } else {
+        print!("{}", report.text(&chip8, batch.sections));
+    }
+
+    let mut code = 0;
+    if report.finish == Finish::Frames && !batch.until.is_empty() {
+        let hint = if batch.frames.is_none() { " (the default limit; raise it with --frames)" } else { "" };
+        eprintln!("chip8: no --until condition was met in {} frames{}", report.frames, hint);
+        code = 1;
+    }
+    for (expression, expected) in &batch.expect {
+        match evaluate(&chip8, expression) {
+            Ok(value) if value == *expected => {}
+            Ok(value) => {
+                eprintln!("chip8: expected {} = {:X}, got {:X}", expression, expected, value);
+                code = 1;
+            }
+            Err(err) => {
+                eprintln!("chip8: --expect {}: {}", expression, err);
+                code = 1;
+            }
+        }
+    }
+
+    code
+}
+
+/// Read the movie at `path` and make sure it was recorded with `rom`.
+fn read_movie(path: &str, rom: &str) -> Movie {
+    let movie = Movie::read(Path::new(path)).unwrap_or_else(|err| {
+        eprintln!("chip8: cannot read `{}`: {}", path, err);
+        process::exit(1);
+    });
+    let bytes = fs::read(rom).unwrap_or_else(|err| {
+        eprintln!("chip8: cannot read `{}`: {}", rom, err);
+        process::exit(1);
+    });
+    if let Err(err) = movie.check_rom(&bytes) {
+        eprintln!("chip8: `{}`: {}", path, err);
+        process::exit(1);
+    }
+
+    movie
+}
+
+fn disasm(rom: &str, platform: Platform) {
+    let bytes = fs::read(rom).unwrap_or_else(|err| {
+        eprintln!("chip8: cannot read `{}`: {}", rom, err);
+        process::exit(1);
+    });
+
+    print!("{}", disassemble(&bytes, platform));
+}
+
+fn asm(source: &str, output: Option<String>, platform: Platform) {
+    let rom = assemble_file(Path::new(source), platform).unwrap_or_else(|err| {
+        eprintln!("chip8: {}", err);
+        process::exit(1);
+    });
+
+    let output = output.unwrap_or_else(|| Path::new(source).with_extension("ch8").display().to_string());
+    if let Err(err) = fs::write(&output, &rom) {
+        eprintln!("chip8: cannot write `{}`: {}", output, err);
+        process::exit(1);
+    }
+}
+
+fn run(machine: &mut Machine, options: &Options) -> i32 {
+    let mut clock = FrameClock::new();
+    let mut debugger = Debugger::new();
+    let mut console = if options.debug || !options.breakpoints.is_empty() { Some(Console::spawn()) } else { None };
+    let mut paused = options.debug;
+    if paused {
+        print!("{}", debugger.stopped(&mut machine.chip8));
+        prompt(&console);
+    }
+
+    loop {
+        // A line typed while running stops the machine and runs as a command.
+        if let Some(line) = console.as_mut().and_then(Console::line) {
+            paused = true;
+            let (out, outcome) = debugger.execute(&mut machine.chip8, &line);
+            print!("{}", out);
+            match outcome {
+                Outcome::Quit => return 0,
+                Outcome::Running => paused = false,
+                Outcome::Paused => prompt(&console),
+            }
+        }
+
+        // Nobody is left to type commands, so don't wait for them.
+        if paused && console.as_ref().is_none_or(Console::closed) {
+            paused = false;
+        }
+
+        if paused {
+            let commands = machine.idle();
+            if commands.contains(&Command::Quit) {
+                return 0;
+            }
+            quick_save(machine, &options.rom, &commands);
+            clock.wait();
+            continue;
+        }
+
+        let result = machine.frame();
+
+        if options.trace {
+            if let Some(trace) = &mut machine.chip8.trace {
+                for record in trace.drain() {
+                    eprintln!("{}", record);
+                }
+            }
+        }
+
+        match result {
+            Ok(commands) => {
+                if commands.contains(&Command::Quit) || machine.chip8.halted {
+                    return 0;
+                }
+                let finished = machine.replay.as_ref().filter(|replay| replay.finished()).map(|replay| replay.frame);
+                if let Some(frames) = finished {
+                    eprintln!("chip8: replay finished after {} frames", frames);
+                    if options.headless {
+                        return 0;
+                    }
+                    // Hand the keypad back to the player.
+                    machine.replay = None;
+                }
+                quick_save(machine, &options.rom, &commands);
+            }
+            Err(err) => {
+                eprintln!("chip8: {}", err);
+                machine.chip8.print_debug();
+                return 1;
+            }
+        }
+
+        if machine.chip8.stop.is_some() {
+            print!("{}", debugger.stopped(&mut machine.chip8));
+            paused = true;
+            prompt(&console);
+        }
+
+        if !options.headless {
+            clock.wait();
+        }
+    }
+}
+
+/// Handle the quick-save slot commands. Slot `n` of `game.ch8` lives in
+/// `game.ch8.state<n>` next to the ROM. Loading is refused while a movie is
+/// recorded or replayed, as a movie can't reproduce the jump.
+fn quick_save(machine: &mut Machine, rom: &str, commands: &[Command]) {
+    let movie = machine.recording.is_some() || machine.replay.is_some();
+    let chip8 = &mut machine.chip8;
+    for command in commands {
+        let (slot, load) = match *command {
+            Command::SaveState(slot) => (slot, false),
+            Command::LoadState(slot) => (slot, true),
+            _ => continue,
+        };
+        if load && movie {
+            eprintln!("chip8: slot {}: can't load a state during a movie", slot);
+            continue;
+        }
+
+        let path = format!("{}.state{}", rom, slot);
+        let result = if load { savestate::read(chip8, Path::new(&path)) } else { savestate::write(chip8, Path::new(&path)) };
+        match result {
+            Ok(()) if load => eprintln!("chip8: loaded state from slot {}", slot),
+            Ok(()) => eprintln!("chip8: saved state to slot {}", slot),
+            Err(err) => eprintln!("chip8: slot {}: {}", slot, err),
+        }
+    }
+}
+
+fn prompt(console: &Option<Console>) {
+    if let Some(console) = console {
+        console.prompt();
+    }
+}
+
+fn headless(options: &Options) -> Result<Frontends, String> {
+    let audio: Box<dyn Audio> = match &options.wav {
+        Some(path) => {
+            let recorder = WavRecorder::create(path, options.tone)
+                .map_err(|err| format!("cannot create `{}`: {}", path, err))?;
+            Box::new(recorder)
+        }
+        None => Box::new(NullAudio),
+    };
+
+    Ok((Box::new(NullDisplay), Box::new(NullInput), audio))
+}
+
+#[cfg(feature = "sdl")]
+fn window(options: &Options) -> Result<Frontends, String> {
+    use crate::sdl::{SdlAudio, SdlDisplay, SdlInput};
+    use chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+    let sdl_context = sdl2::init()?;
+
+    let display = SdlDisplay::new(&sdl_context, SCREEN_WIDTH, SCREEN_HEIGHT, options.scale, &options.palette)?;
+    let input = SdlInput::new(&sdl_context, &keymap(options)?)?;
+    let audio: Box<dyn Audio> = match SdlAudio::new(&sdl_context, options.tone) {
+        Ok(audio) => Box::new(audio),
+        Err(err) => {
+            eprintln!("chip8: no audio: {}", err);
+            Box::new(NullAudio)
+        }
+    };
+
+    Ok((Box::new(display), Box::new(input), audio))
+}
+
+/// The layout from `--keymap`, or from the keymap file in the config
+/// directory if there is one, as it applies to the ROM being run.
+#[cfg(feature = "sdl")]
+fn keymap(options: &Options) -> Result<chip8::keymap::Keymap, String> {
+    use chip8::keymap::Keymap;
+
+    let rom = Path::new(&options.rom).file_name().and_then(|name| name.to_str());
+    match &options.keymap {
+        Some(path) => Keymap::load(Path::new(path), rom),
+        None => match default_keymap_path().filter(|path| path.exists()) {
+            Some(path) => Keymap::load(&path, rom),
+            None => Ok(Keymap::default()),
+        },
+    }
+}
+
+/// `$XDG_CONFIG_HOME/chip8/keymap`, falling back to `~/.config`.
+#[cfg(feature = "sdl")]
+fn default_keymap_path() -> Option<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("chip8").join("keymap"))
+}
+
+#[cfg(not(feature = "sdl"))]
+fn window(_options: &Options) -> Result<Frontends, String> {
+    Err("this build has no window support; use --headless or rebuild with the `sdl` feature".to_string())
+}
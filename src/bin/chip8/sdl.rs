@@ -0,0 +1,279 @@
+//! SDL2 backend: a window for `Display`, the keyboard and game controllers
+//! for `Input` and an audio device for `Audio`.
+
+use std::collections::{HashMap, HashSet};
+
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::pixels::Color;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::video::Window;
+use sdl2::render::Canvas;
+use sdl2::rect::Rect;
+use sdl2::{EventPump, GameControllerSubsystem, Sdl};
+
+use chip8::audio::{Oscillator, Tone, SAMPLE_RATE};
+use chip8::frontend::{Audio, Command, Display, Input};
+use chip8::keymap::Keymap;
+
+pub struct SdlDisplay {
+    canvas: Canvas<Window>,
+    /// The resolution the canvas is currently scaled from.
+    size: (usize, usize),
+    palette: [Color; 4],
+}
+
+impl SdlDisplay {
+    pub fn new(sdl_context: &Sdl, width: usize, height: usize, scale: u32, colors: &[u32; 4]) -> Result<SdlDisplay, String> {
+        let video_subsystem = sdl_context.video()?;
+
+        let window = video_subsystem.window("chip8", width as u32 * scale, height as u32 * scale)
+            .position_centered()
+            .build()
+            .map_err(|err| format!("could not initialize video subsystem: {}", err))?;
+
+        let mut canvas = window.into_canvas().build()
+            .map_err(|err| format!("could not make a canvas: {}", err))?;
+        canvas.set_logical_size(width as u32, height as u32)
+            .map_err(|err| format!("could not scale the canvas: {}", err))?;
+
+        canvas.set_draw_color(palette(colors)[0]);
+        canvas.clear();
+        canvas.present();
+
+        Ok(SdlDisplay {
+            canvas,
+            size: (width, height),
+            palette: palette(colors),
+        })
+    }
+}
+
+impl Display for SdlDisplay {
+    fn draw(&mut self, screen: &[u8], width: usize, height: usize) {
+        // The renderer scales CHIP-8 pixels up to the window, so lo-res and
+        // hi-res screens both fill it whatever --scale was.
+        if self.size != (width, height) {
+            let _ = self.canvas.set_logical_size(width as u32, height as u32);
+            self.size = (width, height);
+        }
+
+        for (i, pixel) in screen.iter().enumerate() {
+            self.canvas.set_draw_color(self.palette[*pixel as usize & 0b11]);
+            let _ = self.canvas.fill_rect(Rect::new((i % width) as i32, (i / width) as i32, 1, 1));
+        }
+
+        self.canvas.present();
+    }
+}
+
+fn palette(colors: &[u32; 4]) -> [Color; 4] {
+    let mut palette = [Color::RGB(0, 0, 0); 4];
+    for (color, rgb) in palette.iter_mut().zip(colors.iter()) {
+        *color = Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, *rgb as u8);
+    }
+
+    palette
+}
+
+/// A bindable host input: a keyboard key, a controller button, or a stick
+/// or trigger pushed past the threshold in one direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Host {
+    Key(Keycode),
+    Button(Button),
+    /// The axis and whether it is the positive direction.
+    Axis(Axis, bool),
+}
+
+/// Stands in for the controller id of keys held on the keyboard.
+const KEYBOARD: i32 = -1;
+
+pub struct SdlInput {
+    event_pump: EventPump,
+    /// `None` if SDL could not start its controller support.
+    controllers: Option<GameControllerSubsystem>,
+    /// Open controllers by joystick id; they close when dropped.
+    pads: HashMap<i32, GameController>,
+    /// The hex keys each bound host input presses, one bit per key.
+    bindings: HashMap<Host, u16>,
+    /// How far from the centre an axis has to be to count as pushed.
+    threshold: i16,
+    /// Bound inputs currently down, by controller id.
+    held: HashSet<(i32, Host)>,
+    /// Backspace is held down.
+    rewinding: bool,
+}
+
+impl SdlInput {
+    pub fn new(sdl_context: &Sdl, keymap: &Keymap) -> Result<SdlInput, String> {
+        let mut bindings = HashMap::new();
+        for (name, key) in keymap.bindings() {
+            let host = host(name).ok_or_else(|| format!("unknown key `{}` in keymap", name))?;
+            *bindings.entry(host).or_insert(0) |= 1 << key;
+        }
+
+        // Controllers already plugged in are announced as added devices on
+        // the first poll, just like ones plugged in later.
+        let controllers = sdl_context.game_controller().map_err(|err| eprintln!("chip8: no controllers: {}", err)).ok();
+
+        Ok(SdlInput {
+            event_pump: sdl_context.event_pump()?,
+            controllers,
+            pads: HashMap::new(),
+            bindings,
+            threshold: (i16::MAX as i32 * keymap.threshold as i32 / 100) as i16,
+            held: HashSet::new(),
+            rewinding: false,
+        })
+    }
+
+    fn press(&mut self, id: i32, host: Host, down: bool) {
+        if !down {
+            self.held.remove(&(id, host));
+        } else if self.bindings.contains_key(&host) {
+            self.held.insert((id, host));
+        }
+    }
+
+    fn connect(&mut self, index: u32) {
+        let controllers = match &self.controllers {
+            Some(controllers) => controllers,
+            None => return,
+        };
+
+        match controllers.open(index) {
+            Ok(pad) => {
+                eprintln!("chip8: controller connected: {}", pad.name());
+                self.pads.insert(pad.instance_id(), pad);
+            }
+            Err(err) => eprintln!("chip8: cannot open controller {}: {}", index, err),
+        }
+    }
+
+    fn disconnect(&mut self, id: i32) {
+        if let Some(pad) = self.pads.remove(&id) {
+            eprintln!("chip8: controller disconnected: {}", pad.name());
+        }
+        self.held.retain(|(held, _)| *held != id);
+    }
+}
+
+impl Input for SdlInput {
+    fn poll(&mut self, keypad: &mut [bool]) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        for event in events {
+            match event {
+                Event::Quit {..} |
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => { commands.push(Command::Quit); }
+                Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => self.rewinding = true,
+                Event::KeyUp { keycode: Some(Keycode::Backspace), .. } => self.rewinding = false,
+                Event::KeyDown { keycode: Some(keycode), repeat: false, .. } if slot(keycode).is_some() => {
+                    if let Some((slot, load)) = slot(keycode) {
+                        commands.push(if load { Command::LoadState(slot) } else { Command::SaveState(slot) });
+                    }
+                }
+                Event::KeyDown { keycode: Some(keycode), .. } => self.press(KEYBOARD, Host::Key(keycode), true),
+                Event::KeyUp { keycode: Some(keycode), .. } => self.press(KEYBOARD, Host::Key(keycode), false),
+                Event::ControllerDeviceAdded { which, .. } => self.connect(which),
+                Event::ControllerDeviceRemoved { which, .. } => self.disconnect(which),
+                Event::ControllerButtonDown { which, button, .. } => self.press(which, Host::Button(button), true),
+                Event::ControllerButtonUp { which, button, .. } => self.press(which, Host::Button(button), false),
+                Event::ControllerAxisMotion { which, axis, value, .. } => {
+                    self.press(which, Host::Axis(axis, false), value < -self.threshold);
+                    self.press(which, Host::Axis(axis, true), value > self.threshold);
+                }
+                _ => {}
+            }
+        }
+
+        // A hex key stays down while any host input bound to it is.
+        let pressed = self.held.iter().fold(0, |bits, (_, host)| bits | self.bindings[host]);
+        for (i, key) in keypad.iter_mut().enumerate() {
+            *key = pressed & (1 << i) != 0;
+        }
+
+        if self.rewinding {
+            commands.push(Command::Rewind);
+        }
+
+        commands
+    }
+}
+
+/// Parse a keymap name: `Pad BUTTON`, `Pad AXIS+`/`Pad AXIS-`, or an SDL
+/// key name.
+fn host(name: &str) -> Option<Host> {
+    let pad = name.strip_prefix("Pad ").or_else(|| name.strip_prefix("pad "));
+    match pad.map(str::trim) {
+        Some(axis) if axis.ends_with('+') || axis.ends_with('-') => {
+            let (axis, sign) = axis.split_at(axis.len() - 1);
+            Axis::from_string(&axis.to_ascii_lowercase()).map(|axis| Host::Axis(axis, sign == "+"))
+        }
+        Some(button) => Button::from_string(&button.to_ascii_lowercase()).map(Host::Button),
+        None => Keycode::from_name(name).map(Host::Key),
+    }
+}
+
+/// F1-F4 save to quick-save slots 1-4 and F5-F8 load them again. Returns
+/// the slot and whether it is a load.
+fn slot(keycode: Keycode) -> Option<(u8, bool)> {
+    match keycode {
+        Keycode::F1 => Some((1, false)),
+        Keycode::F2 => Some((2, false)),
+        Keycode::F3 => Some((3, false)),
+        Keycode::F4 => Some((4, false)),
+        Keycode::F5 => Some((1, true)),
+        Keycode::F6 => Some((2, true)),
+        Keycode::F7 => Some((3, true)),
+        Keycode::F8 => Some((4, true)),
+        _ => None,
+    }
+}
+
+struct Beeper {
+    oscillator: Oscillator,
+}
+
+impl AudioCallback for Beeper {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.oscillator.fill(out);
+    }
+}
+
+pub struct SdlAudio {
+    device: AudioDevice<Beeper>,
+}
+
+impl SdlAudio {
+    pub fn new(sdl_context: &Sdl, tone: Tone) -> Result<SdlAudio, String> {
+        let audio_subsystem = sdl_context.audio()?;
+        let desired = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let device = audio_subsystem.open_playback(None, &desired, |spec| {
+            Beeper { oscillator: Oscillator::new(tone, spec.freq as u32) }
+        })?;
+        device.resume();
+
+        Ok(SdlAudio { device })
+    }
+}
+
+impl Audio for SdlAudio {
+    fn update(&mut self, beeping: bool) {
+        self.device.lock().oscillator.playing = beeping;
+    }
+
+    fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        self.device.lock().oscillator.set_pattern(pattern, pitch);
+    }
+}
@@ -0,0 +1,929 @@
+use std::fs;
+
+use crate::error::Chip8Error;
+use crate::instruction::Instruction;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::rewind::Rewind;
+use crate::rng::Rng;
+use crate::trace::{Trace, TraceRecord};
+use crate::watch::{Access, Target, WatchHit, Watchpoint};
+
+pub const APP_START: u16 = 0x200;
+pub const SCREEN_WIDTH: usize = 64;
+pub const SCREEN_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+const STACK_SIZE: usize = 16;
+
+/// An `FX0A` in progress: the machine is halted until a key goes down and
+/// comes back up, as on the COSMAC VIP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyWait {
+    pub register: u8,
+    pub pressed: Option<u8>,
+}
+
+/// Why `run_frame` returned before finishing the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The program counter reached a breakpoint; the instruction there has
+    /// not run yet.
+    Breakpoint(u16),
+    /// The instruction that just ran triggered a watchpoint.
+    Watchpoint(WatchHit),
+}
+
+pub struct Chip8 {
+    pub registers: Vec<u8>,
+    pub memory: Vec<u8>,
+    pub index: u16,
+    pub size: usize,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub stack_pointer: u8,
+    /// Row-major `width * height` framebuffer; resized by `00FE`/`00FF`.
+    /// Each pixel is a bitmask of the XO-CHIP planes lit at that position,
+    /// so it is 0 or 1 everywhere else.
+    pub screen: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+    pub hires: bool,
+    /// XO-CHIP bitplanes selected by `FN01`; drawing, clearing and
+    /// scrolling only touch these.
+    pub planes: u8,
+    pub cycles: usize,
+    pub timer: u8,
+    pub keypad: Vec<bool>,
+    pub key_wait: Option<KeyWait>,
+    pub sound_timer: u8,
+    /// XO-CHIP audio pattern loaded by `F002`, 128 one-bit samples. Until a
+    /// ROM loads one the plain beeper tone is used.
+    pub audio_pattern: Option<[u8; 16]>,
+    /// XO-CHIP playback pitch set by `FX3A`.
+    pub pitch: u8,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    /// SUPER-CHIP RPL user flags, saved and restored by `FX75`/`FX85`.
+    pub flags: Vec<u8>,
+    /// Set by `00FD`; a halted machine no longer executes instructions.
+    pub halted: bool,
+    /// Source of `CXNN` random bytes; see `seed`.
+    pub rng: Rng,
+    /// Addresses `run_frame` stops at before executing.
+    pub breakpoints: Vec<u16>,
+    /// Checked around every instruction; see `Stop::Watchpoint`.
+    pub watchpoints: Vec<Watchpoint>,
+    /// Set when `run_frame` stopped early; cleared by whoever handles it.
+    pub stop: Option<Stop>,
+    /// The first memory watchpoint the current instruction triggered.
+    memory_hit: Option<WatchHit>,
+    /// Set by `DXYN` under the `display_wait` quirk to end the current frame.
+    pub vblank_wait: bool,
+    /// The most recently executed instructions; `None` turns tracing off.
+    pub trace: Option<Trace>,
+    /// History for `step_back`; `None` turns rewinding off.
+    pub rewind: Option<Rewind>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[allow(dead_code)]
+impl Chip8 {
+    const SPRITE_LOCATION: u8 = 0x50;
+    const BIG_SPRITE_LOCATION: u8 = 0xA0;
+
+    pub fn new() -> Chip8 {
+        Self::with_quirks(Quirks::default())
+    }
+
+    pub fn with_quirks(quirks: Quirks) -> Chip8 {
+        Self::with_platform(Platform::Chip8, quirks)
+    }
+
+    pub fn with_platform(platform: Platform, quirks: Quirks) -> Chip8 {
+        let mut chip8 = Chip8 {
+            registers: (0..=15).map(|_| 0).collect(),
+            memory: (0..platform.memory_size()).map(|_| 0).collect(),
+            index: 0,
+            pc: 0,
+            stack: Vec::with_capacity(STACK_SIZE),
+            stack_pointer: 0,
+            screen: (0..(SCREEN_WIDTH * SCREEN_HEIGHT)).map(|_| 0).collect(),
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT,
+            hires: false,
+            planes: 1,
+            size: 0,
+            cycles: 0,
+            timer: 0,
+            keypad: (0..=15).map(|_| false).collect(),
+            key_wait: None,
+            sound_timer: 0,
+            audio_pattern: None,
+            pitch: 64,
+            platform,
+            quirks,
+            flags: (0..platform.flag_count()).map(|_| 0).collect(),
+            halted: false,
+            rng: Rng::from_entropy(),
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            stop: None,
+            memory_hit: None,
+            vblank_wait: false,
+            trace: Some(Trace::default()),
+            rewind: None,
+        };
+
+        chip8.load_fonts();
+        chip8
+    }
+
+    fn load_fonts(&mut self) {
+        let fonts = vec![
+            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+            0x20, 0x60, 0x20, 0x20, 0x70, // 1
+            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+            0xF0, 0x80, 0xF0, 0x80, 0x80  // F
+        ];
+
+        for (i, x) in fonts.iter().enumerate() {
+            self.memory[Self::SPRITE_LOCATION as usize + i] = *x;
+        }
+
+        let big_fonts = vec![
+            0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+            0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+            0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+            0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+            0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+            0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+            0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+            0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+            0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+            0x18, 0x3C, 0x66, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+            0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+            0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+            0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF, // E
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0  // F
+        ];
+
+        for (i, x) in big_fonts.iter().enumerate() {
+            self.memory[Self::BIG_SPRITE_LOCATION as usize + i] = *x;
+        }
+    }
+
+    /// Switch between the 64x32 and 128x64 framebuffers, clearing the screen.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        if hires {
+            self.width = HIRES_WIDTH;
+            self.height = HIRES_HEIGHT;
+        } else {
+            self.width = SCREEN_WIDTH;
+            self.height = SCREEN_HEIGHT;
+        }
+
+        self.screen = (0..(self.width * self.height)).map(|_| 0).collect();
+    }
+
+    /// Move the selected planes by `dx` columns and `dy` rows (positive is
+    /// right and down), filling the uncovered edge with blank pixels.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let mask = self.planes;
+        let (width, height) = (self.width as isize, self.height as isize);
+        let old = self.screen.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (source_x, source_y) = (x - dx, y - dy);
+                let source = if source_x >= 0 && source_x < width && source_y >= 0 && source_y < height {
+                    old[(source_y * width + source_x) as usize]
+                } else {
+                    0
+                };
+
+                let i = (y * width + x) as usize;
+                self.screen[i] = (self.screen[i] & !mask) | (source & mask);
+            }
+        }
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<(), Chip8Error> {
+        let content = fs::read(path)?;
+        self.load_rom(&content)
+    }
+
+    /// Copy a ROM image to `APP_START` and start running it from there.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let start_address: u16 = APP_START;
+        let max = self.memory.len() - start_address as usize;
+        if rom.len() > max {
+            return Err(Chip8Error::RomTooLarge { size: rom.len(), max });
+        }
+
+        for (i, x) in rom.iter().enumerate() {
+            self.memory[start_address as usize + i] = *x;
+        }
+
+        self.pc = APP_START;
+        self.size = rom.len();
+
+        Ok(())
+    }
+
+    pub fn print_debug(&self) {
+        for i in 0..=0xF {
+            print!("V{:X}: {:X} | ", i, self.registers[i]);
+        }
+
+        println!("\n");
+
+        for i in 0..=0xF {
+           print!("{:?}: {:?} | ", i, self.keypad[i]);
+        }
+
+        println!("\n");
+
+        println!("pc {:X}\n", self.pc);
+        println!("index {:X}\n", self.index);
+        println!("cycles {:?}\n", self.cycles);
+        println!("timer {:?}\n", self.timer);
+        println!("seed {}\n", self.rng.seed);
+
+        let opcode1 = self.memory.get(self.pc as usize).copied().unwrap_or(0);
+        let opcode2 = self.memory.get(self.pc as usize + 1).copied().unwrap_or(0);
+        println!("opcode {:02X}{:02X} \n", opcode1, opcode2);
+
+        if let Some(trace) = &self.trace {
+            for record in trace.iter() {
+                println!("{}", record);
+            }
+        }
+    }
+
+    pub fn cycle(&mut self) -> Result<(), Chip8Error> {
+        if self.halted {
+            return Ok(());
+        }
+
+        if let Some(mut rewind) = self.rewind.take() {
+            rewind.record(self);
+            self.rewind = Some(rewind);
+        }
+
+        if self.pc as usize + 1 >= self.memory.len() {
+            return Err(Chip8Error::PcOutOfBounds { pc: self.pc });
+        }
+
+        if self.key_wait.is_some() {
+            self.poll_key_wait();
+            return Ok(());
+        }
+
+        let opcode_1 = self.memory[self.pc as usize] >> 4;
+        let opcode_2 = self.memory[self.pc as usize] & 0x0F;
+
+        self.pc = self.pc.wrapping_add(1);
+        let opcode_3 = self.memory[self.pc as usize] >> 4;
+        let opcode_4 = self.memory[self.pc as usize] & 0x0F;
+        
+        self.pc = self.pc.wrapping_add(1);
+
+        let opcode = (opcode_1, opcode_2, opcode_3, opcode_4);
+        self.cycles += 1;
+
+        if self.trace.is_none() && self.watchpoints.is_empty() {
+            return self.execute_opcode(opcode);
+        }
+
+        let pc = self.pc.wrapping_sub(2);
+        let raw = (self.memory[pc as usize] as u16) << 8 | self.memory[pc as usize + 1] as u16;
+        let mut record = TraceRecord {
+            cycle: self.cycles,
+            pc,
+            opcode: raw,
+            instruction: None,
+            registers_before: [0; 16],
+            registers_after: [0; 16],
+            index_before: self.index,
+            index_after: self.index,
+        };
+        record.registers_before.copy_from_slice(&self.registers);
+        if self.trace.is_some() {
+            record.instruction = Instruction::decode(&self.memory[pc as usize..], self.platform);
+        }
+        let timers_before = (self.timer, self.sound_timer);
+
+        // Failing instructions are recorded too; they are the interesting ones.
+        let result = self.execute_opcode(opcode);
+
+        record.registers_after.copy_from_slice(&self.registers);
+        record.index_after = self.index;
+
+        if !self.watchpoints.is_empty() {
+            let hit = self.memory_hit.take().or_else(|| self.register_hit(&record, timers_before));
+            if let Some(hit) = hit {
+                self.stop = Some(Stop::Watchpoint(WatchHit { pc, opcode: raw, ..hit }));
+            }
+        }
+
+        if let Some(trace) = &mut self.trace {
+            trace.push(record);
+        }
+
+        result
+    }
+
+    /// The first register watchpoint an instruction triggered, judging by the
+    /// registers before and after it.
+    fn register_hit(&self, record: &TraceRecord, timers_before: (u8, u8)) -> Option<WatchHit> {
+        self.watchpoints.iter().enumerate().find_map(|(i, watchpoint)| {
+            let (old, new) = match watchpoint.target {
+                Target::Memory { .. } => return None,
+                Target::V(x) => (record.registers_before[x as usize] as u16, record.registers_after[x as usize] as u16),
+                Target::I => (record.index_before, record.index_after),
+                Target::Delay => (timers_before.0 as u16, self.timer as u16),
+                Target::Sound => (timers_before.1 as u16, self.sound_timer as u16),
+            };
+
+            if old == new {
+                return None;
+            }
+
+            Some(WatchHit { watchpoint: i, access: Access::Change, address: None, old, new, pc: 0, opcode: 0 })
+        })
+    }
+
+    /// Read a byte on behalf of an instruction, checking read watchpoints.
+    fn read_memory(&mut self, address: usize) -> u8 {
+        let value = self.memory[address];
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints(address, Access::Read, value, value);
+        }
+
+        value
+    }
+
+    /// Every write an instruction makes to memory goes through here so write
+    /// and change watchpoints see it.
+    fn write_memory(&mut self, address: usize, value: u8) {
+        if !self.watchpoints.is_empty() {
+            self.check_watchpoints(address, Access::Write, self.memory[address], value);
+        }
+
+        self.memory[address] = value;
+    }
+
+    fn check_watchpoints(&mut self, address: usize, access: Access, old: u8, new: u8) {
+        if self.memory_hit.is_some() {
+            return;
+        }
+
+        let address = address as u16;
+        let found = self.watchpoints.iter().position(|watchpoint| watchpoint.matches_memory(address, access, old, new));
+        if let Some(i) = found {
+            self.memory_hit = Some(WatchHit {
+                watchpoint: i,
+                access,
+                address: Some(address),
+                old: old as u16,
+                new: new as u16,
+                pc: 0,
+                opcode: 0,
+            });
+        }
+    }
+
+    /// Count the delay and sound timers down by one. Call this at 60 Hz,
+    /// independently of how many instructions run in between.
+    pub fn tick_timers(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+        }
+
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+    }
+
+    /// Make `CXNN` produce the same sequence on every run. Machines start
+    /// with a random seed, recorded in `rng.seed`.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Run one 60 Hz frame: `instructions` cycles followed by a timer tick.
+    /// Under the `display_wait` quirk the frame ends early after a draw. If
+    /// a breakpoint is reached the frame is abandoned with `stop` set, timers
+    /// untouched; step past it with `cycle` before running the next frame.
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), Chip8Error> {
+        self.run_frame_until(instructions, |_| false)
+    }
+
+    /// `run_frame`, but also abandon the frame, timers untouched, as soon as
+    /// `until` holds before an instruction.
+    pub fn run_frame_until<F: FnMut(&Chip8) -> bool>(&mut self, instructions: usize, mut until: F) -> Result<(), Chip8Error> {
+        self.vblank_wait = false;
+        for _ in 0..instructions {
+            if !self.breakpoints.is_empty() && self.breakpoints.contains(&self.pc) {
+                self.stop = Some(Stop::Breakpoint(self.pc));
+                return Ok(());
+            }
+            if until(self) {
+                return Ok(());
+            }
+
+            self.cycle()?;
+            if self.stop.is_some() {
+                return Ok(());
+            }
+            if self.vblank_wait {
+                break;
+            }
+        }
+
+        self.tick_timers();
+        Ok(())
+    }
+
+    /// Advance a pending `FX0A`: remember the first key that goes down and
+    /// store it in Vx once it is released.
+    fn poll_key_wait(&mut self) {
+        let wait = match self.key_wait {
+            Some(wait) => wait,
+            None => return,
+        };
+
+        match wait.pressed {
+            None => {
+                if let Some(key) = self.keypad.iter().position(|pressed| *pressed) {
+                    self.key_wait = Some(KeyWait { pressed: Some(key as u8), ..wait });
+                }
+            }
+            Some(key) => {
+                if !self.keypad[key as usize] {
+                    self.registers[wait.register as usize] = key;
+                    self.key_wait = None;
+                }
+            }
+        }
+    }
+
+    /// Skip the next instruction, which on XO-CHIP may be the four-byte
+    /// `F000 NNNN`.
+    fn skip(&mut self) {
+        let next = self.pc as usize;
+        if self.platform == Platform::XoChip && next + 1 < self.memory.len()
+            && self.memory[next] == 0xF0 && self.memory[next + 1] == 0x00 {
+            self.pc = self.pc.wrapping_add(4);
+        } else {
+            self.pc = self.pc.wrapping_add(2);
+        }
+    }
+
+    /// Fails with `MemoryOutOfBounds` unless `len` bytes starting at `start`
+    /// are all inside memory.
+    fn check_memory(&self, start: usize, len: usize, pc: u16, opcode: u16) -> Result<(), Chip8Error> {
+        if start + len > self.memory.len() {
+            let address = start.max(self.memory.len());
+            return Err(Chip8Error::MemoryOutOfBounds { pc, opcode, address });
+        }
+
+        Ok(())
+    }
+
+    fn execute_opcode(&mut self, opcode: (u8, u8, u8, u8)) -> Result<(), Chip8Error> {
+        let pc = self.pc.wrapping_sub(2);
+        let raw = (opcode.0 as u16) << 12 | (opcode.1 as u16) << 8 | (opcode.2 as u16) << 4 | opcode.3 as u16;
+
+        match opcode {
+            (0, 0, 0xE, 0xE) => {
+                // Return from a subroutine
+                self.pc = self.stack.pop().ok_or(Chip8Error::StackUnderflow { pc, opcode: raw })?;
+            },
+            (1, n1, n2, n3) => {
+                // Jump to location nnn
+                let addr: u16 = (n1 as u16) << 8 | (n2 as u16) << 4 | (n3 as u16);
+                self.pc = addr;
+            },
+            (2, n1, n2, n3) => {
+                // Call subroutine at nnn.
+                if self.stack.len() >= STACK_SIZE {
+                    return Err(Chip8Error::StackOverflow { pc, opcode: raw });
+                }
+
+                self.stack.push(self.pc);
+                let addr: u16 = (n1 as u16) << 8 | (n2 as u16) << 4 | (n3 as u16);
+                self.pc = addr;
+            },
+            (3, x, k1, k2) => {
+                //  Skip next instruction if Vx = kk.
+                let vx = self.registers[x as usize];
+                let kk: u8 = (k1 << 4) | k2; 
+                if vx == kk {
+                    self.skip();
+                }
+            },
+            (4, x, k1, k2) => {
+                // Skip next instruction if Vx != kk.
+                let vx = self.registers[x as usize];
+                let kk: u8 = (k1 << 4) | k2; 
+                if vx != kk {
+                    self.skip();
+                }
+            },
+            (5, x, y, 0) => {
+                // Skip next instruction if Vx = Vy
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+                if vx == vy {
+                    self.skip();
+                }
+            },
+            (6, x, k1, k2) => {
+                // Vx = kk
+                let k: u8 = (k1 << 4) | k2; 
+                self.registers[x as usize] = k;
+            },
+            (7, x, k1, k2) => {
+                // Vx = Vx + kk
+                let kk: u8 = (k1 << 4) | k2; 
+                self.registers[x as usize] = (kk  as u16 + self.registers[x as usize] as u16) as u8;
+            },
+            (8, x, y, 0) => {
+                // Set Vx = Vy.
+                self.registers[x as usize] = self.registers[y as usize];
+            },
+            (8, x, y, 1) => {
+                // Set Vx = Vx OR Vy.
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+
+                self.registers[x as usize] = vx | vy;
+                if self.quirks.vf_reset {
+                    self.registers[0xF] = 0;
+                }
+            },
+            (8, x, y, 2) => {
+                // Vx = Vx AND Vy
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+
+                self.registers[x as usize] = vx & vy;
+                if self.quirks.vf_reset {
+                    self.registers[0xF] = 0;
+                }
+            },
+            (8, x, y, 3) => {
+                // Vx = Vx XOR Vy
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+
+                self.registers[x as usize] = vx ^ vy;
+                if self.quirks.vf_reset {
+                    self.registers[0xF] = 0;
+                }
+            },
+            (8, x, y, 4) => {
+                // Set Vx = Vx + Vy, set VF = carry.
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+                let (value, carry) = vx.overflowing_add(vy);
+
+                // VF is written last so the flag wins when x is F.
+                self.registers[x as usize] = value;
+                self.registers[0xF] = carry as u8;
+            },
+            (8, x, y, 5) => {
+                // Set Vx = Vx - Vy, set VF = NOT borrow.
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+
+                self.registers[x as usize] = vx.wrapping_sub(vy);
+                self.registers[0xF] = (vx >= vy) as u8;
+            },
+            (8, x, k , 6) => {
+                // Set Vx = Vx SHR 1 (Vx = Vy SHR 1 under the shift_vy quirk).
+                if self.quirks.shift_vy {
+                    self.registers[x as usize] = self.registers[k as usize];
+                }
+
+                let value = self.registers[x as usize];
+                self.registers[x as usize] = value >> 1;
+                self.registers[0xF] = value & 0b0000_0001;
+            },
+            (8, x, y, 7) => {
+                // Set Vx = Vy - Vx, set VF = NOT borrow.
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+
+                self.registers[x as usize] = vy.wrapping_sub(vx);
+                self.registers[0xF] = (vy >= vx) as u8;
+            },
+            (8, x, k, 0xE) => {
+                //  Set Vx = Vx SHL 1 (Vx = Vy SHL 1 under the shift_vy quirk).
+                if self.quirks.shift_vy {
+                    self.registers[x as usize] = self.registers[k as usize];
+                }
+
+                let value = self.registers[x as usize];
+                self.registers[x as usize] = value << 1;
+                self.registers[0xF] = value >> 7;
+            },
+            (9, x, y, 0) => {
+                //  Skip next instruction if Vx != Vy.
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+                if vx != vy {
+                    self.skip();
+                }
+            },
+            (0xA, n1, n2, n3) => {
+                // Set I = nnn.
+                self.index = (n1 as u16) << 8 | (n2 as u16) << 4 | (n3 as u16);
+            },
+            (0xB, n1, n2, n3) => {
+                //  Jump to location nnn + V0 (xnn + Vx under the jump_vx quirk).
+                let v0 = if self.quirks.jump_vx { self.registers[n1 as usize] } else { self.registers[0] };
+                let addr = (n1 as u16) << 8 | (n2 as u16) << 4 | (n3 as u16);
+                self.pc = v0 as u16 + addr;
+            },
+            (0xC, x, k1, k2) => {
+                // Set Vx = random byte AND kk.
+                let kk: u8 = (k1 << 4) | k2; 
+                let value = self.rng.byte();
+                self.registers[x as usize] = value & kk;
+            },
+            (0xD, vx, vy, n) => {
+                // Draw sprite, x, y, number of bytes (a 16x16 sprite when n is 0 on SUPER-CHIP)
+                // The starting position always wraps; the pixels that run off
+                // the edge are clipped unless the wrap_sprites quirk is on.
+                let base_x = self.registers[vx as usize] as usize % self.width;
+                let base_y = self.registers[vy as usize] as usize % self.height;
+                let (rows, columns) = if n == 0 && self.platform != Platform::Chip8 { (16, 16) } else { (n as usize, 8) };
+                let bytes_per_row = columns / 8;
+                // XO-CHIP draws one sprite per selected plane, stored back to back.
+                let planes: Vec<u8> = [1, 2].iter().copied().filter(|plane| self.planes & plane != 0).collect();
+                // SUPER-CHIP in hi-res sets VF to the number of rows that
+                // collided or were clipped off the bottom instead of to 1.
+                let count_rows = self.platform == Platform::SuperChip && self.hires;
+
+                let mut collided_rows = 0;
+                self.check_memory(self.index as usize, planes.len() * rows * bytes_per_row, pc, raw)?;
+
+                for (layer, plane) in planes.into_iter().enumerate() {
+                    for row in 0..rows {
+                        let mut y = base_y + row;
+                        if y >= self.height {
+                            if !self.quirks.wrap_sprites {
+                                if count_rows {
+                                    collided_rows += rows - row;
+                                }
+                                break;
+                            }
+
+                            y %= self.height;
+                        }
+
+                        let address = self.index as usize + (layer * rows + row) * bytes_per_row;
+                        let sprite = if bytes_per_row == 2 {
+                            (self.read_memory(address) as u16) << 8 | self.read_memory(address + 1) as u16
+                        } else {
+                            (self.read_memory(address) as u16) << 8
+                        };
+
+                        let mut collided = false;
+                        for column in 0..columns {
+                            let mut x = base_x + column;
+                            if x >= self.width {
+                                if !self.quirks.wrap_sprites {
+                                    break;
+                                }
+
+                                x %= self.width;
+                            }
+
+                            let pixel = (sprite >> (15 - column)) & 0b0000_0001 == 1;
+                            if pixel {
+                                let coordinates = (y * self.width) + x;
+                                if self.screen[coordinates] & plane != 0 {
+                                    collided = true;
+                                }
+
+                                self.screen[coordinates] ^= plane;
+                            }
+                        }
+
+                        if collided {
+                            collided_rows += 1;
+                        }
+                    }
+                }
+
+                self.registers[0xF] = if count_rows { collided_rows as u8 } else { (collided_rows > 0) as u8 };
+                self.vblank_wait = self.quirks.display_wait;
+            },
+            (0xE, x, 9, 0xE) => {
+                // Skip next instruction if key with the value of Vx is pressed.
+                let vx = self.registers[x as usize] & 0xF;
+                if self.keypad[vx as usize] {
+                    self.skip();
+                }
+            },
+            (0xE, x, 0xA, 1) => {
+                // Skip next instruction if key with the value of Vx is not pressed.
+                let vx = self.registers[x as usize] & 0xF;
+                if !self.keypad[vx as usize] {
+                    self.skip();
+                }
+            },
+            (0xF, x, 0, 0xA) => {
+                // Wait for a key press and release, store the value of the key in Vx.
+                self.key_wait = Some(KeyWait { register: x, pressed: None });
+            },
+            (0xF, x, 1, 5) => {
+                // Set delay timer = Vx
+                let vx = self.registers[x as usize];
+                self.timer = vx;
+            },
+            (0xF, x, 0, 7) => {
+                // Set Vx = delay timer value.
+                self.registers[x as usize] = self.timer;
+            },
+            (0xF, x, 1, 8) => {
+                // Set sound timer = Vx.
+                self.sound_timer = self.registers[x as usize];
+            },
+            (0xF, x, 1, 0xE) => {
+                // Set I = I + Vx.
+                self.index = self.index.wrapping_add(self.registers[x as usize] as u16);
+            },
+            (0xF, x, 2, 9) => {
+                // Set I = location of sprite for digit Vx.
+                let vx = self.registers[x as usize];
+                self.index = vx as u16 * 5 + Self::SPRITE_LOCATION as u16;
+            },
+            (0xF, x, 3, 3) => {
+                // Store BCD representation of Vx in memory locations I, I+1, and I+2.
+                let vx = self.registers[x as usize];
+                self.check_memory(self.index as usize, 3, pc, raw)?;
+                self.write_memory(self.index as usize, vx / 100);
+                self.write_memory(self.index as usize + 1, (vx % 100) / 10);
+                self.write_memory(self.index as usize + 2, vx % 10);
+            },
+            (0xF, x, 5, 5) => {
+                //  Store registers V0 through Vx in memory starting at location I.
+                self.check_memory(self.index as usize, x as usize + 1, pc, raw)?;
+                for i in 0..=x {
+                    self.write_memory(self.index as usize + i as usize, self.registers[i as usize]);
+                }
+
+                if self.quirks.memory_increment {
+                    self.index = self.index.wrapping_add(x as u16 + 1);
+                }
+            },
+            (0xF, x, 6, 5) => {
+                // Read registers V0 through Vx from memory starting at location I.
+                self.check_memory(self.index as usize, x as usize + 1, pc, raw)?;
+                for i in 0..=x {
+                    let value = self.read_memory(self.index as usize + i as usize);
+                    self.registers[i as usize] = value;
+                }
+
+                if self.quirks.memory_increment {
+                    self.index = self.index.wrapping_add(x as u16 + 1);
+                }
+            },
+            (0x0, 0x0, 0xE, 0) => {
+                // CLEAN SCREEN
+                for pixel in self.screen.iter_mut() {
+                    *pixel &= !self.planes;
+                }
+            },
+            (0, 0, 0xC, n) if self.platform != Platform::Chip8 => {
+                // Scroll the display down n pixels.
+                self.scroll(0, n as isize);
+            },
+            (0, 0, 0xD, n) if self.platform == Platform::XoChip => {
+                // Scroll the display up n pixels.
+                self.scroll(0, -(n as isize));
+            },
+            (0, 0, 0xF, 0xB) if self.platform != Platform::Chip8 => {
+                // Scroll the display right 4 pixels.
+                self.scroll(4, 0);
+            },
+            (0, 0, 0xF, 0xC) if self.platform != Platform::Chip8 => {
+                // Scroll the display left 4 pixels.
+                self.scroll(-4, 0);
+            },
+            (0, 0, 0xF, 0xD) if self.platform != Platform::Chip8 => {
+                // Exit the interpreter.
+                self.halted = true;
+            },
+            (0, 0, 0xF, 0xE) if self.platform != Platform::Chip8 => {
+                // Switch to 64x32 low-res mode.
+                self.set_hires(false);
+            },
+            (0, 0, 0xF, 0xF) if self.platform != Platform::Chip8 => {
+                // Switch to 128x64 hi-res mode.
+                self.set_hires(true);
+            },
+            (5, x, y, 2) if self.platform == Platform::XoChip => {
+                // Store Vx through Vy (in either order) in memory starting at location I.
+                let registers: Vec<u8> = register_range(x, y).map(|i| self.registers[i]).collect();
+                self.check_memory(self.index as usize, registers.len(), pc, raw)?;
+                let start = self.index as usize;
+                for (offset, value) in registers.into_iter().enumerate() {
+                    self.write_memory(start + offset, value);
+                }
+            },
+            (5, x, y, 3) if self.platform == Platform::XoChip => {
+                // Read Vx through Vy (in either order) from memory starting at location I.
+                let count = register_range(x, y).count();
+                self.check_memory(self.index as usize, count, pc, raw)?;
+                for (offset, i) in register_range(x, y).enumerate() {
+                    self.registers[i] = self.read_memory(self.index as usize + offset);
+                }
+            },
+            (0xF, 0, 0, 0) if self.platform == Platform::XoChip => {
+                // Set I = the 16-bit address in the next two bytes.
+                self.check_memory(self.pc as usize, 2, pc, raw)?;
+                let addr = (self.memory[self.pc as usize] as u16) << 8 | self.memory[self.pc as usize + 1] as u16;
+                self.index = addr;
+                self.pc = self.pc.wrapping_add(2);
+            },
+            (0xF, n, 0, 1) if self.platform == Platform::XoChip => {
+                // Select the bitplanes n (0-3) to draw on.
+                self.planes = n & 0b11;
+            },
+            (0xF, 0, 0, 2) if self.platform == Platform::XoChip => {
+                // Load the 16-byte audio pattern at I.
+                self.check_memory(self.index as usize, 16, pc, raw)?;
+                let mut pattern = [0; 16];
+                for (offset, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.read_memory(self.index as usize + offset);
+                }
+                self.audio_pattern = Some(pattern);
+            },
+            (0xF, x, 3, 0xA) if self.platform == Platform::XoChip => {
+                // Set the audio pattern playback pitch = Vx.
+                self.pitch = self.registers[x as usize];
+            },
+            (0xF, x, 3, 0) if self.platform != Platform::Chip8 => {
+                // Set I = location of the 10-byte big sprite for digit Vx.
+                let vx = self.registers[x as usize] & 0xF;
+                self.index = vx as u16 * 10 + Self::BIG_SPRITE_LOCATION as u16;
+            },
+            (0xF, x, 7, 5) if self.platform != Platform::Chip8 => {
+                // Store V0 through Vx in the RPL user flags.
+                if x as usize >= self.flags.len() {
+                    return Err(Chip8Error::FlagsOutOfRange { pc, opcode: raw, register: x, flags: self.flags.len() });
+                }
+
+                self.flags[..=x as usize].copy_from_slice(&self.registers[..=x as usize]);
+            },
+            (0xF, x, 8, 5) if self.platform != Platform::Chip8 => {
+                // Read V0 through Vx from the RPL user flags.
+                if x as usize >= self.flags.len() {
+                    return Err(Chip8Error::FlagsOutOfRange { pc, opcode: raw, register: x, flags: self.flags.len() });
+                }
+
+                self.registers[..=x as usize].copy_from_slice(&self.flags[..=x as usize]);
+            },
+            _ => {
+                return Err(Chip8Error::UnknownOpcode { pc, opcode: raw });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Register indices from x to y inclusive, counting down when y < x.
+fn register_range(x: u8, y: u8) -> Box<dyn Iterator<Item = usize>> {
+    if x <= y {
+        Box::new(x as usize..=y as usize)
+    } else {
+        Box::new((y as usize..=x as usize).rev())
+    }
+}
@@ -0,0 +1,49 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Rate the delay and sound timers count down at, and the rate frames are
+/// presented at.
+pub const FRAME_RATE: u32 = 60;
+
+/// Default CPU speed in instructions per second.
+pub const DEFAULT_IPS: u32 = 700;
+
+/// How many instructions to run per 60 Hz frame for a given CPU speed.
+pub fn instructions_per_frame(ips: u32) -> usize {
+    ((ips + FRAME_RATE / 2) / FRAME_RATE).max(1) as usize
+}
+
+/// Sleeps the frontend loop so frames are presented at `FRAME_RATE`
+/// regardless of how fast the host runs the emulation.
+pub struct FrameClock {
+    frame: Duration,
+    next: Instant,
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameClock {
+    pub fn new() -> FrameClock {
+        FrameClock {
+            frame: Duration::from_secs(1) / FRAME_RATE,
+            next: Instant::now(),
+        }
+    }
+
+    /// Block until the current frame's time slot is over. If the host fell
+    /// more than a frame behind, resynchronise instead of trying to catch up.
+    pub fn wait(&mut self) {
+        self.next += self.frame;
+
+        let now = Instant::now();
+        if self.next > now {
+            thread::sleep(self.next - now);
+        } else if now - self.next > self.frame {
+            self.next = now;
+        }
+    }
+}
@@ -0,0 +1,579 @@
+//! Command interpreter for interactive debugging.
+//!
+//! The `Debugger` turns command lines into operations on a paused `Chip8` and
+//! returns their output as text, so a frontend only has to collect lines
+//! from somewhere and print what comes back. Addresses and values are hex,
+//! with or without `0x`; counts are decimal.
+
+use std::fmt::Write;
+
+use crate::chip8::{Chip8, Stop};
+use crate::instruction::Instruction;
+use crate::watch::{Access, Target, WatchHit, Watchpoint};
+
+pub const HELP: &str = "commands:
+    break ADDR (b)        stop when PC reaches ADDR; lists breakpoints without ADDR
+    delete ADDR (d)       remove the breakpoint at ADDR
+    step [N] (s)          execute N instructions (default 1)
+    back [N] (sb)         step back N instructions (default 1)
+    continue (c)          resume running
+    regs (r)              show V0-VF, I, PC, SP and the timers
+    set TARGET VALUE...   set V0-VF, I, PC, DT or ST, or bytes from [ADDR] on
+    stack                 show the call stack, innermost last
+    mem ADDR [LEN] (m)    dump LEN bytes of memory (default 40)
+    dis [ADDR] [N]        disassemble N instructions around PC or from ADDR
+    print EXPR (p)        evaluate an expression
+    watch EXPR (w)        show EXPR whenever the machine stops; lists watches without EXPR
+    unwatch N             remove watch N
+    wp TARGET [ACCESS]    stop when an instruction touches TARGET: V0-VF, I, DT, ST
+                          (on change), or ADDR or ADDR-END memory on read, write
+                          or change (default write); lists watchpoints without TARGET
+    unwp N                remove watchpoint N
+    trace [N] (t)         show the last N executed instructions (default 10)
+    quit (q)              exit
+an empty line repeats the last command. expressions add and subtract V0-VF,
+I, PC, SP, DT, ST, hex numbers and [EXPR] memory bytes.";
+
+/// What the frontend should do after a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Stay stopped and wait for the next command.
+    Paused,
+    /// Go back to running frames.
+    Running,
+    Quit,
+}
+
+#[derive(Debug, Default)]
+pub struct Debugger {
+    /// Expressions shown every time the machine stops.
+    pub watches: Vec<String>,
+    last: String,
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Self::default()
+    }
+
+    /// Describe why the machine stopped (taking `chip8.stop`), where it is
+    /// and the current value of every watch.
+    pub fn stopped(&self, chip8: &mut Chip8) -> String {
+        let mut out = String::new();
+        match chip8.stop.take() {
+            Some(Stop::Breakpoint(address)) => writeln!(out, "breakpoint at {:04X}", address).unwrap(),
+            Some(Stop::Watchpoint(hit)) => out.push_str(&watch_hit(chip8, &hit)),
+            None => writeln!(out, "paused").unwrap(),
+        }
+
+        out.push_str(&self.location(chip8));
+        out
+    }
+
+    /// Run one command line. Empty lines repeat the previous command.
+    pub fn execute(&mut self, chip8: &mut Chip8, line: &str) -> (String, Outcome) {
+        let line = if line.trim().is_empty() { self.last.clone() } else { line.trim().to_string() };
+        self.last = line.clone();
+
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return (String::new(), Outcome::Paused),
+        };
+        let args: Vec<&str> = words.collect();
+
+        let result = match command {
+            "b" | "break" => self.set_breakpoint(chip8, &args),
+            "d" | "delete" => delete_breakpoint(chip8, &args),
+            "s" | "step" => return self.step(chip8, &args),
+            "sb" | "back" => return self.step_back(chip8, &args),
+            "c" | "continue" => return self.resume(chip8),
+            "r" | "regs" => Ok(registers(chip8)),
+            "set" => {
+                // Replaying history must not undo the change.
+                let result = set(chip8, &args);
+                chip8.checkpoint();
+                result
+            }
+            "stack" => Ok(stack(chip8)),
+            "m" | "mem" => memory(chip8, &args),
+            "dis" => disassemble(chip8, &args),
+            "p" | "print" => evaluate(chip8, &args.join(" ")).map(|value| format!("{:X}\n", value)),
+            "w" | "watch" => self.watch(chip8, &args),
+            "unwatch" => self.unwatch(&args),
+            "wp" | "watchpoint" => watchpoint(chip8, &args),
+            "unwp" => delete_watchpoint(chip8, &args),
+            "t" | "trace" => trace(chip8, &args),
+            "q" | "quit" => return (String::new(), Outcome::Quit),
+            "h" | "help" | "?" => Ok(format!("{}\n", HELP)),
+            _ => Err(format!("unknown command `{}`; try help", command)),
+        };
+
+        match result {
+            Ok(out) => (out, Outcome::Paused),
+            Err(err) => (format!("error: {}\n", err), Outcome::Paused),
+        }
+    }
+
+    /// The instruction at PC followed by the watches.
+    fn location(&self, chip8: &Chip8) -> String {
+        let mut out = String::new();
+        writeln!(out, "=> {}", instruction_at(chip8, chip8.pc).0).unwrap();
+        for (i, watch) in self.watches.iter().enumerate() {
+            match evaluate(chip8, watch) {
+                Ok(value) => writeln!(out, "  {}: {} = {:X}", i + 1, watch, value).unwrap(),
+                Err(err) => writeln!(out, "  {}: {} = <{}>", i + 1, watch, err).unwrap(),
+            }
+        }
+
+        out
+    }
+
+    fn set_breakpoint(&self, chip8: &mut Chip8, args: &[&str]) -> Result<String, String> {
+        match args {
+            [] => {
+                let list: Vec<String> = chip8.breakpoints.iter().map(|address| format!("{:04X}", address)).collect();
+                Ok(if list.is_empty() { "no breakpoints\n".to_string() } else { format!("{}\n", list.join(" ")) })
+            }
+            [address] => {
+                let address = parse_hex(address)? as u16;
+                if !chip8.breakpoints.contains(&address) {
+                    chip8.breakpoints.push(address);
+                }
+                Ok(format!("breakpoint at {:04X}\n", address))
+            }
+            _ => Err("usage: break [ADDR]".to_string()),
+        }
+    }
+
+    fn step(&mut self, chip8: &mut Chip8, args: &[&str]) -> (String, Outcome) {
+        let count = match args {
+            [] => 1,
+            [count] => match count.parse::<usize>() {
+                Ok(count) => count,
+                Err(_) => return (format!("error: bad count `{}`\n", count), Outcome::Paused),
+            },
+            _ => return ("error: usage: step [N]\n".to_string(), Outcome::Paused),
+        };
+
+        for i in 0..count {
+            if chip8.halted {
+                return ("halted\n".to_string(), Outcome::Paused);
+            }
+            if let Err(err) = chip8.cycle() {
+                return (format!("error: {}\n{}", err, self.location(chip8)), Outcome::Paused);
+            }
+
+            // A breakpoint ends a multi-step early, like it ends a frame.
+            if i + 1 < count && chip8.stop.is_none() && chip8.breakpoints.contains(&chip8.pc) {
+                chip8.stop = Some(Stop::Breakpoint(chip8.pc));
+            }
+            if chip8.stop.is_some() {
+                return (self.stopped(chip8), Outcome::Paused);
+            }
+        }
+
+        (self.location(chip8), Outcome::Paused)
+    }
+
+    fn step_back(&mut self, chip8: &mut Chip8, args: &[&str]) -> (String, Outcome) {
+        let count = match args {
+            [] => 1,
+            [count] => match count.parse::<usize>() {
+                Ok(count) => count,
+                Err(_) => return (format!("error: bad count `{}`\n", count), Outcome::Paused),
+            },
+            _ => return ("error: usage: back [N]\n".to_string(), Outcome::Paused),
+        };
+
+        if chip8.rewind.is_none() {
+            return ("error: rewinding is off\n".to_string(), Outcome::Paused);
+        }
+
+        let undone = chip8.step_back(count);
+        let mut out = String::new();
+        if undone < count {
+            writeln!(out, "stepped back {} instructions, as far as the history goes", undone).unwrap();
+        }
+        out.push_str(&self.location(chip8));
+        (out, Outcome::Paused)
+    }
+
+    /// Step off a breakpoint at PC so it doesn't stop us again straight away.
+    /// That instruction can hit a watchpoint, which stops right there.
+    fn resume(&mut self, chip8: &mut Chip8) -> (String, Outcome) {
+        if chip8.breakpoints.contains(&chip8.pc) {
+            if let Err(err) = chip8.cycle() {
+                return (format!("error: {}\n", err), Outcome::Paused);
+            }
+            if chip8.stop.is_some() {
+                return (self.stopped(chip8), Outcome::Paused);
+            }
+        }
+
+        (String::new(), Outcome::Running)
+    }
+
+    fn watch(&mut self, chip8: &Chip8, args: &[&str]) -> Result<String, String> {
+        if args.is_empty() {
+            let mut out = String::new();
+            for (i, watch) in self.watches.iter().enumerate() {
+                writeln!(out, "{}: {}", i + 1, watch).unwrap();
+            }
+            return Ok(if out.is_empty() { "no watches\n".to_string() } else { out });
+        }
+
+        let expression = args.join(" ");
+        let value = evaluate(chip8, &expression)?;
+        self.watches.push(expression.clone());
+        Ok(format!("{}: {} = {:X}\n", self.watches.len(), expression, value))
+    }
+
+    fn unwatch(&mut self, args: &[&str]) -> Result<String, String> {
+        let number = match args {
+            [number] => number.parse::<usize>().map_err(|_| format!("bad watch number `{}`", number))?,
+            _ => return Err("usage: unwatch N".to_string()),
+        };
+
+        if number == 0 || number > self.watches.len() {
+            return Err(format!("no watch {}", number));
+        }
+
+        self.watches.remove(number - 1);
+        Ok(String::new())
+    }
+}
+
+fn delete_breakpoint(chip8: &mut Chip8, args: &[&str]) -> Result<String, String> {
+    let address = match args {
+        [address] => parse_hex(address)? as u16,
+        _ => return Err("usage: delete ADDR".to_string()),
+    };
+
+    let before = chip8.breakpoints.len();
+    chip8.breakpoints.retain(|breakpoint| *breakpoint != address);
+    if chip8.breakpoints.len() == before {
+        return Err(format!("no breakpoint at {:04X}", address));
+    }
+
+    Ok(String::new())
+}
+
+fn watchpoint(chip8: &mut Chip8, args: &[&str]) -> Result<String, String> {
+    let (target, access) = match args {
+        [] => {
+            let mut out = String::new();
+            for (i, watchpoint) in chip8.watchpoints.iter().enumerate() {
+                writeln!(out, "{}: {}", i + 1, watchpoint).unwrap();
+            }
+            return Ok(if out.is_empty() { "no watchpoints\n".to_string() } else { out });
+        }
+        [target] => (target.parse::<Target>()?, Access::Write),
+        [target, access] => (target.parse::<Target>()?, access.parse::<Access>()?),
+        _ => return Err("usage: wp [TARGET [read|write|change]]".to_string()),
+    };
+
+    let watchpoint = Watchpoint { target, access };
+    chip8.watchpoints.push(watchpoint);
+    Ok(format!("watchpoint {}: {}\n", chip8.watchpoints.len(), watchpoint))
+}
+
+fn delete_watchpoint(chip8: &mut Chip8, args: &[&str]) -> Result<String, String> {
+    let number = match args {
+        [number] => number.parse::<usize>().map_err(|_| format!("bad watchpoint number `{}`", number))?,
+        _ => return Err("usage: unwp N".to_string()),
+    };
+
+    if number == 0 || number > chip8.watchpoints.len() {
+        return Err(format!("no watchpoint {}", number));
+    }
+
+    chip8.watchpoints.remove(number - 1);
+    Ok(String::new())
+}
+
+/// Which watchpoint fired, what changed and the instruction responsible.
+fn watch_hit(chip8: &Chip8, hit: &WatchHit) -> String {
+    let what = match (hit.address, hit.access) {
+        (Some(address), Access::Read) => format!("read {:04X} = {:02X}", address, hit.old),
+        (Some(address), _) => format!("write {:04X}: {:02X} -> {:02X}", address, hit.old, hit.new),
+        (None, _) => {
+            let target = chip8.watchpoints.get(hit.watchpoint).map(|watchpoint| watchpoint.target);
+            match target {
+                Some(Target::I) => format!("I: {:04X} -> {:04X}", hit.old, hit.new),
+                Some(target) => format!("{}: {:02X} -> {:02X}", target, hit.old, hit.new),
+                None => format!("{:X} -> {:X}", hit.old, hit.new),
+            }
+        }
+    };
+
+    // Decode the opcode that ran rather than memory, which it may have rewritten.
+    let mut bytes = vec![(hit.opcode >> 8) as u8, hit.opcode as u8];
+    bytes.extend(chip8.memory.iter().skip(hit.pc as usize + 2).take(2));
+    let instruction = match Instruction::decode(&bytes, chip8.platform) {
+        Some(instruction) => instruction.to_string(),
+        None => "???".to_string(),
+    };
+
+    format!("watchpoint {}: {} by {:04X}: {:04X}  {}\n", hit.watchpoint + 1, what, hit.pc, hit.opcode, instruction)
+}
+
+pub(crate) fn registers(chip8: &Chip8) -> String {
+    let mut out = String::new();
+    for (i, value) in chip8.registers.iter().enumerate() {
+        let separator = if i % 8 == 7 { '\n' } else { ' ' };
+        write!(out, "V{:X}={:02X}{}", i, value, separator).unwrap();
+    }
+
+    writeln!(
+        out,
+        "PC={:04X} I={:04X} SP={:X} DT={:02X} ST={:02X} cycles={}",
+        chip8.pc, chip8.index, chip8.stack.len(), chip8.timer, chip8.sound_timer, chip8.cycles
+    )
+    .unwrap();
+    out
+}
+
+fn set(chip8: &mut Chip8, args: &[&str]) -> Result<String, String> {
+    let (target, values) = match args {
+        [target, values @ ..] if !values.is_empty() => (*target, values),
+        _ => return Err("usage: set TARGET VALUE...".to_string()),
+    };
+
+    if target.starts_with('[') && target.ends_with(']') {
+        let start = evaluate(chip8, &target[1..target.len() - 1])? as usize;
+        if start.saturating_add(values.len()) > chip8.memory.len() {
+            return Err(format!("{:X} is outside memory", start));
+        }
+        for (offset, value) in values.iter().enumerate() {
+            chip8.memory[start + offset] = evaluate(chip8, value)? as u8;
+        }
+        return Ok(String::new());
+    }
+
+    let value = match values {
+        [value] => evaluate(chip8, value)?,
+        _ => return Err(format!("`{}` takes one value", target)),
+    };
+
+    match target.to_ascii_uppercase().as_str() {
+        "I" => chip8.index = value as u16,
+        "PC" => chip8.pc = value as u16,
+        "DT" => chip8.timer = value as u8,
+        "ST" => chip8.sound_timer = value as u8,
+        name => match register(name) {
+            Some(x) => chip8.registers[x] = value as u8,
+            None => return Err(format!("can't set `{}`", target)),
+        },
+    }
+
+    Ok(String::new())
+}
+
+fn stack(chip8: &Chip8) -> String {
+    if chip8.stack.is_empty() {
+        return "stack is empty\n".to_string();
+    }
+
+    let frames: Vec<String> = chip8.stack.iter().map(|address| format!("{:04X}", address)).collect();
+    format!("{}\n", frames.join(" "))
+}
+
+fn memory(chip8: &Chip8, args: &[&str]) -> Result<String, String> {
+    let (start, len) = match args {
+        [start] => (evaluate(chip8, start)? as usize, 0x40),
+        [start, len] => (evaluate(chip8, start)? as usize, evaluate(chip8, len)? as usize),
+        _ => return Err("usage: mem ADDR [LEN]".to_string()),
+    };
+
+    let end = start.saturating_add(len).min(chip8.memory.len());
+    if start >= end {
+        return Err(format!("{:X} is outside memory", start));
+    }
+
+    Ok(hexdump(&chip8.memory[start..end], start))
+}
+
+/// 16 bytes per line, each line starting with the address of its first.
+pub(crate) fn hexdump(bytes: &[u8], start: usize) -> String {
+    let mut out = String::new();
+    for (row, bytes) in bytes.chunks(16).enumerate() {
+        write!(out, "{:04X} ", start + row * 16).unwrap();
+        for byte in bytes {
+            write!(out, " {:02X}", byte).unwrap();
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn disassemble(chip8: &Chip8, args: &[&str]) -> Result<String, String> {
+    let (start, count) = match args {
+        [] => (chip8.pc.saturating_sub(8), 10),
+        [start] => (evaluate(chip8, start)? as u16, 10),
+        [start, count] => {
+            let count = count.parse().map_err(|_| format!("bad count `{}`", count))?;
+            (evaluate(chip8, start)? as u16, count)
+        }
+        _ => return Err("usage: dis [ADDR] [N]".to_string()),
+    };
+
+    let mut out = String::new();
+    let mut address = start;
+    for _ in 0..count {
+        if address as usize + 1 >= chip8.memory.len() {
+            break;
+        }
+
+        let (text, size) = instruction_at(chip8, address);
+        let marker = if address == chip8.pc { "=>" } else { "  " };
+        writeln!(out, "{} {}", marker, text).unwrap();
+        address = address.wrapping_add(size);
+    }
+
+    Ok(out)
+}
+
+fn trace(chip8: &Chip8, args: &[&str]) -> Result<String, String> {
+    let count = match args {
+        [] => 10,
+        [count] => count.parse().map_err(|_| format!("bad count `{}`", count))?,
+        _ => return Err("usage: trace [N]".to_string()),
+    };
+
+    let trace = chip8.trace.as_ref().ok_or("tracing is off")?;
+    let mut out = String::new();
+    for record in trace.iter().skip(trace.len().saturating_sub(count)) {
+        writeln!(out, "{}", record).unwrap();
+    }
+
+    Ok(out)
+}
+
+/// `ADDR: OPCODE  MNEMONIC` for the instruction at `address`, and its size.
+fn instruction_at(chip8: &Chip8, address: u16) -> (String, u16) {
+    let bytes = chip8.memory.get(address as usize..).unwrap_or(&[]);
+    let opcode = match bytes {
+        [high, low, ..] => (*high as u16) << 8 | *low as u16,
+        _ => return (format!("{:04X}: outside memory", address), 2),
+    };
+
+    match Instruction::decode(bytes, chip8.platform) {
+        Some(instruction) => (format!("{:04X}: {:04X}  {}", address, opcode, instruction), instruction.size()),
+        None => (format!("{:04X}: {:04X}  ???", address, opcode), 2),
+    }
+}
+
+fn register(name: &str) -> Option<usize> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('V'), Some(digit), None) | (Some('v'), Some(digit), None) => digit.to_digit(16).map(|x| x as usize),
+        _ => None,
+    }
+}
+
+fn parse_hex(text: &str) -> Result<u32, String> {
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+    u32::from_str_radix(digits, 16).map_err(|_| format!("bad number `{}` (numbers are hex)", text))
+}
+
+/// Evaluate a watch or `print` expression.
+pub fn evaluate(chip8: &Chip8, text: &str) -> Result<i64, String> {
+    let tokens = tokenize(text);
+    if tokens.is_empty() {
+        return Err("expected an expression".to_string());
+    }
+
+    let mut position = 0;
+    let value = expression(chip8, &tokens, &mut position)?;
+    match tokens.get(position) {
+        Some(token) => Err(format!("unexpected `{}`", token)),
+        None => Ok(value),
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+
+        if !word.is_empty() {
+            tokens.push(word.clone());
+            word.clear();
+        }
+        if !c.is_whitespace() {
+            tokens.push(c.to_string());
+        }
+    }
+
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+fn expression(chip8: &Chip8, tokens: &[String], position: &mut usize) -> Result<i64, String> {
+    let mut value = term(chip8, tokens, position)?;
+    while let Some(operator) = tokens.get(*position) {
+        match operator.as_str() {
+            "+" => {
+                *position += 1;
+                value += term(chip8, tokens, position)?;
+            }
+            "-" => {
+                *position += 1;
+                value -= term(chip8, tokens, position)?;
+            }
+            _ => break,
+        }
+    }
+
+    Ok(value)
+}
+
+fn term(chip8: &Chip8, tokens: &[String], position: &mut usize) -> Result<i64, String> {
+    let token = tokens.get(*position).ok_or("expected a value")?;
+    *position += 1;
+
+    let close = |position: &mut usize, close: &str| match tokens.get(*position) {
+        Some(token) if token == close => {
+            *position += 1;
+            Ok(())
+        }
+        _ => Err(format!("expected `{}`", close)),
+    };
+
+    match token.as_str() {
+        "-" => Ok(-term(chip8, tokens, position)?),
+        "(" => {
+            let value = expression(chip8, tokens, position)?;
+            close(position, ")")?;
+            Ok(value)
+        }
+        "[" => {
+            let address = expression(chip8, tokens, position)?;
+            close(position, "]")?;
+            chip8
+                .memory
+                .get(address as usize)
+                .map(|byte| *byte as i64)
+                .ok_or_else(|| format!("{:X} is outside memory", address))
+        }
+        word => match word.to_ascii_uppercase().as_str() {
+            "I" => Ok(chip8.index as i64),
+            "PC" => Ok(chip8.pc as i64),
+            "SP" => Ok(chip8.stack.len() as i64),
+            "DT" => Ok(chip8.timer as i64),
+            "ST" => Ok(chip8.sound_timer as i64),
+            name => match register(name) {
+                Some(x) => Ok(chip8.registers[x] as i64),
+                None => parse_hex(word).map(|value| value as i64),
+            },
+        },
+    }
+}
@@ -0,0 +1,173 @@
+//! Static disassembly of ROM images.
+//!
+//! Code is told apart from data by following control flow from `APP_START`:
+//! only bytes some path of jumps, calls and skips can reach are decoded as
+//! instructions, everything else is listed as `db` data. Jump and call
+//! targets inside the ROM get generated labels (`L2A4` for jumps, `S2B0` for
+//! subroutines) which the listing uses in place of raw addresses.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::chip8::APP_START;
+use crate::instruction::Instruction;
+use crate::platform::Platform;
+
+/// Data bytes listed per `db` line.
+const DATA_PER_LINE: usize = 8;
+
+/// One line of a listing: an instruction, or a run of data bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    /// `None` for data.
+    pub instruction: Option<Instruction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    pub lines: Vec<Line>,
+    pub labels: BTreeMap<u16, String>,
+}
+
+impl Listing {
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// The instruction as text, with jump and call targets replaced by their
+    /// labels.
+    pub fn format(&self, instruction: Instruction) -> String {
+        let labelled = |mnemonic: &str, address: u16| match self.label(address) {
+            Some(label) => format!("{} {}", mnemonic, label),
+            None => instruction.to_string(),
+        };
+
+        match instruction {
+            Instruction::Jump(address) => labelled("JUMP", address),
+            Instruction::Call(address) => labelled("CALL", address),
+            Instruction::JumpOffset(address) => labelled("JMP", address),
+            _ => instruction.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            if let Some(label) = self.label(line.address) {
+                writeln!(f, "{}:", label)?;
+            }
+
+            let text = match line.instruction {
+                Some(instruction) => self.format(instruction),
+                None => {
+                    let bytes: Vec<String> = line.bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+                    format!("db {}", bytes.join(", "))
+                }
+            };
+
+            let raw: String = line.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            writeln!(f, "{:04X}  {:<16}  {}", line.address, raw, text)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Disassemble `rom` as loaded at `APP_START`.
+pub fn disassemble(rom: &[u8], platform: Platform) -> Listing {
+    let end = APP_START as usize + rom.len();
+    let decode = |address: u16| {
+        let offset = (address as usize).checked_sub(APP_START as usize)?;
+        Instruction::decode(rom.get(offset..)?, platform)
+    };
+
+    // Walk every path from the entry point, noting where instructions start.
+    let mut code = BTreeMap::new();
+    let mut jumps = BTreeSet::new();
+    let mut calls = BTreeSet::new();
+    let mut pending = vec![APP_START];
+
+    while let Some(address) = pending.pop() {
+        if code.contains_key(&address) {
+            continue;
+        }
+
+        let instruction = match decode(address) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        code.insert(address, instruction);
+
+        let next = address.wrapping_add(instruction.size());
+        match instruction {
+            Instruction::Jump(target) => {
+                jumps.insert(target);
+                pending.push(target);
+            }
+            Instruction::Call(target) => {
+                calls.insert(target);
+                pending.push(target);
+                pending.push(next);
+            }
+            // The offset is only known at runtime; assume a jump table that
+            // starts at the base address.
+            Instruction::JumpOffset(target) => {
+                jumps.insert(target);
+                pending.push(target);
+            }
+            Instruction::Ret | Instruction::Exit => {}
+            Instruction::SkipEqByte(..) | Instruction::SkipNeByte(..) | Instruction::SkipEq(..)
+            | Instruction::SkipNe(..) | Instruction::SkipKey(_) | Instruction::SkipNoKey(_) => {
+                let skipped = decode(next).map_or(2, |instruction| instruction.size());
+                pending.push(next);
+                pending.push(next.wrapping_add(skipped));
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    let in_rom = |address: &u16| *address >= APP_START && (*address as usize) < end;
+    let mut labels = BTreeMap::new();
+    for &target in jumps.iter().filter(|address| in_rom(address)) {
+        labels.insert(target, format!("L{:03X}", target));
+    }
+    for &target in calls.iter().filter(|address| in_rom(address)) {
+        labels.insert(target, format!("S{:03X}", target));
+    }
+
+    // Lay the ROM out in address order, grouping bytes no path reaches into
+    // data lines that break at labels and instructions.
+    let mut lines = Vec::new();
+    let mut address = APP_START as usize;
+    while address < end {
+        let offset = address - APP_START as usize;
+        if let Some(&instruction) = code.get(&(address as u16)) {
+            let size = instruction.size() as usize;
+            lines.push(Line {
+                address: address as u16,
+                bytes: rom[offset..offset + size].to_vec(),
+                instruction: Some(instruction),
+            });
+            address += size;
+            continue;
+        }
+
+        let mut bytes = vec![rom[offset]];
+        while bytes.len() < DATA_PER_LINE {
+            let next = address + bytes.len();
+            if next >= end || code.contains_key(&(next as u16)) || labels.contains_key(&(next as u16)) {
+                break;
+            }
+            bytes.push(rom[next - APP_START as usize]);
+        }
+
+        let len = bytes.len();
+        lines.push(Line { address: address as u16, bytes, instruction: None });
+        address += len;
+    }
+
+    Listing { lines, labels }
+}
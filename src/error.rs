@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// A fault raised by the machine. Everything that happens while executing an
+/// instruction carries the address it was fetched from and the raw opcode so
+/// a misbehaving ROM can be tracked down from the error alone.
+#[derive(Debug)]
+pub enum Chip8Error {
+    UnknownOpcode { pc: u16, opcode: u16 },
+    StackUnderflow { pc: u16, opcode: u16 },
+    StackOverflow { pc: u16, opcode: u16 },
+    MemoryOutOfBounds { pc: u16, opcode: u16, address: usize },
+    /// `FX75`/`FX85` with Vx beyond the platform's RPL user flags.
+    FlagsOutOfRange { pc: u16, opcode: u16, register: u8, flags: usize },
+    PcOutOfBounds { pc: u16 },
+    RomTooLarge { size: usize, max: usize },
+    /// A save state that is truncated, corrupt or from another version.
+    BadSaveState(String),
+    /// A movie file that can't be parsed or doesn't belong to the ROM.
+    BadMovie(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { pc, opcode } => {
+                write!(f, "{:04X}: unknown opcode {:04X}", pc, opcode)
+            }
+            Chip8Error::StackUnderflow { pc, opcode } => {
+                write!(f, "{:04X}: {:04X} returned with an empty stack", pc, opcode)
+            }
+            Chip8Error::StackOverflow { pc, opcode } => {
+                write!(f, "{:04X}: {:04X} overflowed the stack", pc, opcode)
+            }
+            Chip8Error::MemoryOutOfBounds { pc, opcode, address } => {
+                write!(f, "{:04X}: {:04X} accessed memory out of bounds at {:04X}", pc, opcode, address)
+            }
+            Chip8Error::FlagsOutOfRange { pc, opcode, register, flags } => {
+                write!(f, "{:04X}: {:04X} needs flags up to V{:X} but there are only {}", pc, opcode, register, flags)
+            }
+            Chip8Error::PcOutOfBounds { pc } => write!(f, "program counter out of bounds at {:04X}", pc),
+            Chip8Error::RomTooLarge { size, max } => {
+                write!(f, "rom is {} bytes but only {} fit in memory", size, max)
+            }
+            Chip8Error::BadSaveState(reason) => write!(f, "bad save state: {}", reason),
+            Chip8Error::BadMovie(reason) => write!(f, "bad movie: {}", reason),
+            Chip8Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for Chip8Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Chip8Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Chip8Error {
+    fn from(err: io::Error) -> Self {
+        Chip8Error::Io(err)
+    }
+}
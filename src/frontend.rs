@@ -0,0 +1,62 @@
+//! Traits the machine calls into to show the screen, read the keypad and
+//! drive the beeper. Each backend (SDL, terminal, tests, recorders) implements
+//! the ones it cares about; the `Null*` types stand in for the rest.
+
+/// Something the host asked the machine loop to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    /// Save the machine to the numbered quick-save slot.
+    SaveState(u8),
+    /// Restore the machine from the numbered quick-save slot.
+    LoadState(u8),
+    /// Run the machine backwards for this frame instead of forwards; sent
+    /// every frame while the rewind key is held.
+    Rewind,
+}
+
+/// Default colours for pixel values 0 to 3: background, plane 1, plane 2 and
+/// both planes. Only the first two show up outside XO-CHIP.
+pub const DEFAULT_PALETTE: [u32; 4] = [0x000000, 0xFFFFFF, 0xAAAAAA, 0x555555];
+
+pub trait Display {
+    /// Present `screen`, a row-major `width * height` buffer of pixels whose
+    /// values index a four-colour palette.
+    fn draw(&mut self, screen: &[u8], width: usize, height: usize);
+}
+
+pub trait Input {
+    /// Update `keypad` (one entry per hex key, `true` while held down) from
+    /// the host and return any
+    /// commands meant for the machine loop rather than the CHIP-8 program.
+    fn poll(&mut self, keypad: &mut [bool]) -> Vec<Command>;
+}
+
+pub trait Audio {
+    /// Called once per frame with whether the sound timer is running.
+    fn update(&mut self, beeping: bool);
+
+    /// XO-CHIP: play `pattern`, 128 one-bit samples, at `pitch` instead of
+    /// the plain tone. Backends without pattern support keep beeping.
+    fn set_pattern(&mut self, _pattern: &[u8; 16], _pitch: u8) {}
+}
+
+pub struct NullDisplay;
+
+impl Display for NullDisplay {
+    fn draw(&mut self, _screen: &[u8], _width: usize, _height: usize) {}
+}
+
+pub struct NullInput;
+
+impl Input for NullInput {
+    fn poll(&mut self, _keypad: &mut [bool]) -> Vec<Command> {
+        Vec::new()
+    }
+}
+
+pub struct NullAudio;
+
+impl Audio for NullAudio {
+    fn update(&mut self, _beeping: bool) {}
+}
@@ -0,0 +1,316 @@
+use std::fmt;
+
+use crate::platform::Platform;
+
+/// One decoded instruction. Register operands are register numbers, not
+/// values, so an `Instruction` describes what the program says rather than
+/// what it did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// `00E0`
+    Cls,
+    /// `00EE`
+    Ret,
+    /// `00CN`
+    ScrollDown(u8),
+    /// `00DN` (XO-CHIP)
+    ScrollUp(u8),
+    /// `00FB`
+    ScrollRight,
+    /// `00FC`
+    ScrollLeft,
+    /// `00FD`
+    Exit,
+    /// `00FE`
+    Low,
+    /// `00FF`
+    High,
+    /// `1NNN`
+    Jump(u16),
+    /// `2NNN`
+    Call(u16),
+    /// `3XNN`
+    SkipEqByte(u8, u8),
+    /// `4XNN`
+    SkipNeByte(u8, u8),
+    /// `5XY0`
+    SkipEq(u8, u8),
+    /// `5XY2` (XO-CHIP)
+    StoreRange(u8, u8),
+    /// `5XY3` (XO-CHIP)
+    LoadRange(u8, u8),
+    /// `6XNN`
+    LoadByte(u8, u8),
+    /// `7XNN`
+    AddByte(u8, u8),
+    /// `8XY0`
+    Move(u8, u8),
+    /// `8XY1`
+    Or(u8, u8),
+    /// `8XY2`
+    And(u8, u8),
+    /// `8XY3`
+    Xor(u8, u8),
+    /// `8XY4`
+    Add(u8, u8),
+    /// `8XY5`
+    Sub(u8, u8),
+    /// `8XY6`
+    Shr(u8, u8),
+    /// `8XY7`
+    SubN(u8, u8),
+    /// `8XYE`
+    Shl(u8, u8),
+    /// `9XY0`
+    SkipNe(u8, u8),
+    /// `ANNN`
+    LoadIndex(u16),
+    /// `BNNN`
+    JumpOffset(u16),
+    /// `CXNN`
+    Random(u8, u8),
+    /// `DXYN`
+    Draw(u8, u8, u8),
+    /// `EX9E`
+    SkipKey(u8),
+    /// `EXA1`
+    SkipNoKey(u8),
+    /// `FX07`
+    GetDelay(u8),
+    /// `FX0A`
+    WaitKey(u8),
+    /// `FX15`
+    SetDelay(u8),
+    /// `FX18`
+    SetSound(u8),
+    /// `FX1E`
+    AddIndex(u8),
+    /// `FX29`
+    Font(u8),
+    /// `FX30`
+    BigFont(u8),
+    /// `FX33`
+    Bcd(u8),
+    /// `FX55`
+    Store(u8),
+    /// `FX65`
+    Load(u8),
+    /// `FX75`
+    StoreFlags(u8),
+    /// `FX85`
+    LoadFlags(u8),
+    /// `F000 NNNN` (XO-CHIP)
+    LoadLongIndex(u16),
+    /// `FN01` (XO-CHIP)
+    Plane(u8),
+    /// `F002` (XO-CHIP)
+    Audio,
+    /// `FX3A` (XO-CHIP)
+    Pitch(u8),
+}
+
+impl Instruction {
+    /// Decode the instruction at the start of `bytes`, or `None` if it is not
+    /// an instruction on `platform` (or `bytes` is cut short).
+    pub fn decode(bytes: &[u8], platform: Platform) -> Option<Instruction> {
+        use self::Instruction::*;
+
+        let (high, low) = (*bytes.first()?, *bytes.get(1)?);
+        let nibbles = (high >> 4, high & 0xF, low >> 4, low & 0xF);
+        let (x, y, n) = (nibbles.1, nibbles.2, nibbles.3);
+        let nnn = (high as u16 & 0xF) << 8 | low as u16;
+        let schip = platform != Platform::Chip8;
+        let xochip = platform == Platform::XoChip;
+
+        let instruction = match nibbles {
+            (0, 0, 0xE, 0) => Cls,
+            (0, 0, 0xE, 0xE) => Ret,
+            (0, 0, 0xC, n) if schip => ScrollDown(n),
+            (0, 0, 0xD, n) if xochip => ScrollUp(n),
+            (0, 0, 0xF, 0xB) if schip => ScrollRight,
+            (0, 0, 0xF, 0xC) if schip => ScrollLeft,
+            (0, 0, 0xF, 0xD) if schip => Exit,
+            (0, 0, 0xF, 0xE) if schip => Low,
+            (0, 0, 0xF, 0xF) if schip => High,
+            (1, ..) => Jump(nnn),
+            (2, ..) => Call(nnn),
+            (3, ..) => SkipEqByte(x, low),
+            (4, ..) => SkipNeByte(x, low),
+            (5, _, _, 0) => SkipEq(x, y),
+            (5, _, _, 2) if xochip => StoreRange(x, y),
+            (5, _, _, 3) if xochip => LoadRange(x, y),
+            (6, ..) => LoadByte(x, low),
+            (7, ..) => AddByte(x, low),
+            (8, _, _, 0) => Move(x, y),
+            (8, _, _, 1) => Or(x, y),
+            (8, _, _, 2) => And(x, y),
+            (8, _, _, 3) => Xor(x, y),
+            (8, _, _, 4) => Add(x, y),
+            (8, _, _, 5) => Sub(x, y),
+            (8, _, _, 6) => Shr(x, y),
+            (8, _, _, 7) => SubN(x, y),
+            (8, _, _, 0xE) => Shl(x, y),
+            (9, _, _, 0) => SkipNe(x, y),
+            (0xA, ..) => LoadIndex(nnn),
+            (0xB, ..) => JumpOffset(nnn),
+            (0xC, ..) => Random(x, low),
+            (0xD, ..) => Draw(x, y, n),
+            (0xE, _, 9, 0xE) => SkipKey(x),
+            (0xE, _, 0xA, 1) => SkipNoKey(x),
+            (0xF, 0, 0, 0) if xochip => {
+                let (high, low) = (*bytes.get(2)?, *bytes.get(3)?);
+                LoadLongIndex((high as u16) << 8 | low as u16)
+            }
+            (0xF, _, 0, 1) if xochip => Plane(x),
+            (0xF, 0, 0, 2) if xochip => Audio,
+            (0xF, _, 0, 7) => GetDelay(x),
+            (0xF, _, 0, 0xA) => WaitKey(x),
+            (0xF, _, 1, 5) => SetDelay(x),
+            (0xF, _, 1, 8) => SetSound(x),
+            (0xF, _, 1, 0xE) => AddIndex(x),
+            (0xF, _, 2, 9) => Font(x),
+            (0xF, _, 3, 0) if schip => BigFont(x),
+            (0xF, _, 3, 3) => Bcd(x),
+            (0xF, _, 3, 0xA) if xochip => Pitch(x),
+            (0xF, _, 5, 5) => Store(x),
+            (0xF, _, 6, 5) => Load(x),
+            (0xF, _, 7, 5) if schip => StoreFlags(x),
+            (0xF, _, 8, 5) if schip => LoadFlags(x),
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+
+    /// How many bytes the instruction takes up in memory.
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LoadLongIndex(_) => 4,
+            _ => 2,
+        }
+    }
+
+    /// The bytes `decode` turns back into this instruction. Operands are
+    /// masked to the width of their field.
+    pub fn encode(&self) -> Vec<u8> {
+        use self::Instruction::*;
+
+        let xyn = |op: u16, x: u8, y: u8, n: u8| op << 12 | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4 | (n as u16 & 0xF);
+        let xnn = |op: u16, x: u8, nn: u8| op << 12 | (x as u16 & 0xF) << 8 | nn as u16;
+        let nnn = |op: u16, nnn: u16| op << 12 | (nnn & 0xFFF);
+
+        let opcode = match *self {
+            Cls => 0x00E0,
+            Ret => 0x00EE,
+            ScrollDown(n) => xyn(0, 0, 0xC, n),
+            ScrollUp(n) => xyn(0, 0, 0xD, n),
+            ScrollRight => 0x00FB,
+            ScrollLeft => 0x00FC,
+            Exit => 0x00FD,
+            Low => 0x00FE,
+            High => 0x00FF,
+            Jump(addr) => nnn(1, addr),
+            Call(addr) => nnn(2, addr),
+            SkipEqByte(x, nn) => xnn(3, x, nn),
+            SkipNeByte(x, nn) => xnn(4, x, nn),
+            SkipEq(x, y) => xyn(5, x, y, 0),
+            StoreRange(x, y) => xyn(5, x, y, 2),
+            LoadRange(x, y) => xyn(5, x, y, 3),
+            LoadByte(x, nn) => xnn(6, x, nn),
+            AddByte(x, nn) => xnn(7, x, nn),
+            Move(x, y) => xyn(8, x, y, 0),
+            Or(x, y) => xyn(8, x, y, 1),
+            And(x, y) => xyn(8, x, y, 2),
+            Xor(x, y) => xyn(8, x, y, 3),
+            Add(x, y) => xyn(8, x, y, 4),
+            Sub(x, y) => xyn(8, x, y, 5),
+            Shr(x, y) => xyn(8, x, y, 6),
+            SubN(x, y) => xyn(8, x, y, 7),
+            Shl(x, y) => xyn(8, x, y, 0xE),
+            SkipNe(x, y) => xyn(9, x, y, 0),
+            LoadIndex(addr) => nnn(0xA, addr),
+            JumpOffset(addr) => nnn(0xB, addr),
+            Random(x, nn) => xnn(0xC, x, nn),
+            Draw(x, y, n) => xyn(0xD, x, y, n),
+            SkipKey(x) => xnn(0xE, x, 0x9E),
+            SkipNoKey(x) => xnn(0xE, x, 0xA1),
+            GetDelay(x) => xnn(0xF, x, 0x07),
+            WaitKey(x) => xnn(0xF, x, 0x0A),
+            SetDelay(x) => xnn(0xF, x, 0x15),
+            SetSound(x) => xnn(0xF, x, 0x18),
+            AddIndex(x) => xnn(0xF, x, 0x1E),
+            Font(x) => xnn(0xF, x, 0x29),
+            BigFont(x) => xnn(0xF, x, 0x30),
+            Bcd(x) => xnn(0xF, x, 0x33),
+            Store(x) => xnn(0xF, x, 0x55),
+            Load(x) => xnn(0xF, x, 0x65),
+            StoreFlags(x) => xnn(0xF, x, 0x75),
+            LoadFlags(x) => xnn(0xF, x, 0x85),
+            LoadLongIndex(addr) => return vec![0xF0, 0x00, (addr >> 8) as u8, addr as u8],
+            Plane(n) => xnn(0xF, n, 0x01),
+            Audio => 0xF002,
+            Pitch(x) => xnn(0xF, x, 0x3A),
+        };
+
+        vec![(opcode >> 8) as u8, opcode as u8]
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Instruction::*;
+
+        match *self {
+            Cls => write!(f, "CLS"),
+            Ret => write!(f, "RET"),
+            ScrollDown(n) => write!(f, "SCD {}", n),
+            ScrollUp(n) => write!(f, "SCU {}", n),
+            ScrollRight => write!(f, "SCR"),
+            ScrollLeft => write!(f, "SCL"),
+            Exit => write!(f, "EXIT"),
+            Low => write!(f, "LOW"),
+            High => write!(f, "HIGH"),
+            Jump(addr) => write!(f, "JUMP 0x{:03X}", addr),
+            Call(addr) => write!(f, "CALL 0x{:03X}", addr),
+            SkipEqByte(x, nn) => write!(f, "JE V{:X}, 0x{:02X}", x, nn),
+            SkipNeByte(x, nn) => write!(f, "JNE V{:X}, 0x{:02X}", x, nn),
+            SkipEq(x, y) => write!(f, "JE V{:X}, V{:X}", x, y),
+            StoreRange(x, y) => write!(f, "MOV [I], V{:X}-V{:X}", x, y),
+            LoadRange(x, y) => write!(f, "MOV V{:X}-V{:X}, [I]", x, y),
+            LoadByte(x, nn) => write!(f, "MOV V{:X}, 0x{:02X}", x, nn),
+            AddByte(x, nn) => write!(f, "ADD V{:X}, 0x{:02X}", x, nn),
+            Move(x, y) => write!(f, "MOV V{:X}, V{:X}", x, y),
+            Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Add(x, y) => write!(f, "ADC V{:X}, V{:X}", x, y),
+            Sub(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Shr(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            SubN(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Shl(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SkipNe(x, y) => write!(f, "JNE V{:X}, V{:X}", x, y),
+            LoadIndex(addr) => write!(f, "MOV I, 0x{:03X}", addr),
+            JumpOffset(addr) => write!(f, "JMP 0x{:03X}", addr),
+            Random(x, nn) => write!(f, "RND V{:X}, 0x{:02X}", x, nn),
+            Draw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            SkipKey(x) => write!(f, "SKP V{:X}", x),
+            SkipNoKey(x) => write!(f, "SKNP V{:X}", x),
+            GetDelay(x) => write!(f, "MOV V{:X}, DT", x),
+            WaitKey(x) => write!(f, "MOV V{:X}, K", x),
+            SetDelay(x) => write!(f, "MOV DT, V{:X}", x),
+            SetSound(x) => write!(f, "MOV ST, V{:X}", x),
+            AddIndex(x) => write!(f, "ADD I, V{:X}", x),
+            Font(x) => write!(f, "FONT V{:X}", x),
+            BigFont(x) => write!(f, "HFONT V{:X}", x),
+            Bcd(x) => write!(f, "BCD V{:X}", x),
+            Store(x) => write!(f, "MOV [I], V{:X}", x),
+            Load(x) => write!(f, "MOV V{:X}, [I]", x),
+            StoreFlags(x) => write!(f, "MOV R, V{:X}", x),
+            LoadFlags(x) => write!(f, "MOV V{:X}, R", x),
+            LoadLongIndex(addr) => write!(f, "MOV I, LONG 0x{:04X}", addr),
+            Plane(n) => write!(f, "PLANE {}", n),
+            Audio => write!(f, "AUDIO"),
+            Pitch(x) => write!(f, "PITCH V{:X}", x),
+        }
+    }
+}
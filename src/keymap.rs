@@ -0,0 +1,169 @@
+//! Which host keys press which of the 16 hex keys.
+//!
+//! The default is the usual COSMAC VIP layout on the left of a QWERTY
+//! keyboard:
+//!
+//! ```text
+//! 1 2 3 C        1 2 3 4
+//! 4 5 6 D   <-   Q W E R
+//! 7 8 9 E        A S D F
+//! A 0 B F        Z X C V
+//! ```
+//!
+//! Game controllers drive the directions with the d-pad and the left stick
+//! (up 2, left 4, right 6, down 8) and press 5 with A and 0 with B.
+//!
+//! A keymap file changes this one hex key at a time. Host keys are named the
+//! way SDL names them: keyboard keys such as `Q`, `Up` or `Keypad 5`, and
+//! controller inputs as `Pad` followed by a button (`Pad A`, `Pad DPUp`,
+//! `Pad Start`) or a stick axis and direction (`Pad LeftX-`, `Pad LeftY+`).
+//! A hex key may have several host keys, binding a host key moves it off the
+//! hex key it had, and a line with no host keys unbinds the hex key.
+//! `threshold` sets how far, in percent, a stick must be pushed to count.
+//! Lines under a `[ROM]` heading only apply when running the ROM with that
+//! file name and win over the lines outside one:
+//!
+//! ```text
+//! # hex key = host keys
+//! 5 = W, Up
+//! 8 = S, Down
+//! threshold = 40
+//!
+//! [pong.ch8]
+//! 1 = Up, Pad DPUp
+//! 4 = Down, Pad DPDown
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    /// Host key names for each hex key.
+    pub keys: [Vec<String>; 16],
+    /// How far a stick has to be pushed, in percent, to press its keys.
+    pub threshold: u8,
+}
+
+/// Default controller bindings.
+const CONTROLLER: [(&str, u8); 10] = [
+    ("Pad DPUp", 0x2), ("Pad DPLeft", 0x4), ("Pad DPRight", 0x6), ("Pad DPDown", 0x8),
+    ("Pad LeftY-", 0x2), ("Pad LeftX-", 0x4), ("Pad LeftX+", 0x6), ("Pad LeftY+", 0x8),
+    ("Pad A", 0x5), ("Pad B", 0x0),
+];
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self::cosmac();
+        for (name, key) in CONTROLLER.iter() {
+            keymap.keys[*key as usize].push(name.to_string());
+        }
+
+        keymap
+    }
+}
+
+impl Keymap {
+    /// The COSMAC keyboard layout alone, without controller bindings.
+    pub fn cosmac() -> Keymap {
+        const LAYOUT: [(&str, u8); 16] = [
+            ("1", 0x1), ("2", 0x2), ("3", 0x3), ("4", 0xC),
+            ("Q", 0x4), ("W", 0x5), ("E", 0x6), ("R", 0xD),
+            ("A", 0x7), ("S", 0x8), ("D", 0x9), ("F", 0xE),
+            ("Z", 0xA), ("X", 0x0), ("C", 0xB), ("V", 0xF),
+        ];
+
+        let mut keymap = Keymap { keys: Default::default(), threshold: 50 };
+        for (name, key) in LAYOUT.iter() {
+            keymap.keys[*key as usize].push(name.to_string());
+        }
+
+        keymap
+    }
+
+    /// The default layout changed by the keymap file at `path` for `rom`.
+    pub fn load(path: &Path, rom: Option<&str>) -> Result<Keymap, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("cannot read `{}`: {}", path.display(), err))?;
+        let mut keymap = Keymap::default();
+        keymap.apply(&text, rom).map_err(|err| format!("{}:{}", path.display(), err))?;
+        Ok(keymap)
+    }
+
+    /// Apply a keymap file's bindings: the general ones, then the ones under
+    /// the heading for `rom`.
+    pub fn apply(&mut self, text: &str, rom: Option<&str>) -> Result<(), String> {
+        let mut general = Vec::new();
+        let mut specific = Vec::new();
+        let mut section: Option<&str> = None;
+
+        for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+            let line = line.find('#').map_or(line, |hash| &line[..hash]).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                if !line.ends_with(']') || line.len() < 3 {
+                    return Err(format!("{}: expected `[ROM]`", number));
+                }
+                section = Some(line[1..line.len() - 1].trim());
+                continue;
+            }
+
+            let setting = parse_setting(line).map_err(|err| format!("{}: {}", number, err))?;
+            match section {
+                None => general.push(setting),
+                Some(name) if Some(name) == rom => specific.push(setting),
+                Some(_) => {}
+            }
+        }
+
+        for setting in general.into_iter().chain(specific) {
+            match setting {
+                Setting::Bind(key, names) => self.bind(key, names),
+                Setting::Threshold(percent) => self.threshold = percent,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Make `names` the host keys for `key`, taking them off any other key.
+    pub fn bind(&mut self, key: u8, names: Vec<String>) {
+        for other in self.keys.iter_mut() {
+            other.retain(|old| !names.iter().any(|name| name.eq_ignore_ascii_case(old)));
+        }
+        self.keys[key as usize & 0xF] = names;
+    }
+
+    /// Every `(host key, hex key)` pair.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, u8)> {
+        self.keys.iter().enumerate().flat_map(|(key, names)| names.iter().map(move |name| (name.as_str(), key as u8)))
+    }
+}
+
+enum Setting {
+    Bind(u8, Vec<String>),
+    Threshold(u8),
+}
+
+/// `HEX = NAME, NAME...` or `threshold = PERCENT`.
+fn parse_setting(line: &str) -> Result<Setting, String> {
+    let eq = line.find('=').ok_or_else(|| format!("expected `KEY = HOST KEYS`, got `{}`", line))?;
+    let digit = line[..eq].trim();
+    if digit == "threshold" {
+        let value = line[eq + 1..].trim();
+        return match value.parse::<u8>() {
+            Ok(percent) if (1..=100).contains(&percent) => Ok(Setting::Threshold(percent)),
+            _ => Err(format!("bad threshold `{}` (expected a percentage from 1 to 100)", value)),
+        };
+    }
+
+    let key = match u8::from_str_radix(digit, 16) {
+        Ok(key) if digit.len() == 1 => key,
+        _ => return Err(format!("`{}` is not a hex key (expected 0-F)", digit)),
+    };
+
+    let names = line[eq + 1..].split(',').map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect();
+    Ok(Setting::Bind(key, names))
+}
@@ -0,0 +1,35 @@
+//! Headless CHIP-8 core.
+//!
+//! The `Chip8` machine owns registers, memory, stack, timers, the framebuffer
+//! and the keypad, and knows nothing about windows or audio devices. Frontends
+//! implement the traits in `frontend` and hand them to a `Machine`, which
+//! calls into them as it runs (see `src/bin/chip8` for the SDL one).
+
+pub mod asm;
+pub mod audio;
+mod chip8;
+pub mod clock;
+pub mod debugger;
+pub mod disasm;
+mod error;
+pub mod frontend;
+mod instruction;
+pub mod keymap;
+mod machine;
+pub mod movie;
+mod platform;
+pub mod quirks;
+pub mod rewind;
+mod rng;
+pub mod runner;
+pub mod savestate;
+pub mod trace;
+pub mod watch;
+
+pub use crate::chip8::{Chip8, KeyWait, Stop, APP_START, HIRES_HEIGHT, HIRES_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+pub use crate::error::Chip8Error;
+pub use crate::instruction::Instruction;
+pub use crate::machine::Machine;
+pub use crate::platform::Platform;
+pub use crate::quirks::Quirks;
+pub use crate::rng::Rng;
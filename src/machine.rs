@@ -0,0 +1,78 @@
+use crate::chip8::Chip8;
+use crate::clock::{instructions_per_frame, DEFAULT_IPS};
+use crate::error::Chip8Error;
+use crate::frontend::{Audio, Command, Display, Input};
+use crate::movie::{keypad_bits, Movie, Replay};
+
+/// A `Chip8` wired to a display, an input source and an audio sink.
+pub struct Machine {
+    pub chip8: Chip8,
+    pub instructions_per_frame: usize,
+    /// Collects the keypad of every frame that runs.
+    pub recording: Option<Movie>,
+    /// Drives the keypad instead of the input backend until it runs out.
+    pub replay: Option<Replay>,
+    display: Box<dyn Display>,
+    input: Box<dyn Input>,
+    audio: Box<dyn Audio>,
+}
+
+impl Machine {
+    pub fn new(chip8: Chip8, display: Box<dyn Display>, input: Box<dyn Input>, audio: Box<dyn Audio>) -> Machine {
+        Machine {
+            chip8,
+            instructions_per_frame: instructions_per_frame(DEFAULT_IPS),
+            recording: None,
+            replay: None,
+            display,
+            input,
+            audio,
+        }
+    }
+
+    /// Set the CPU speed in instructions per second.
+    pub fn set_ips(&mut self, ips: u32) {
+        self.instructions_per_frame = instructions_per_frame(ips);
+    }
+
+    /// Poll input, run one 60 Hz frame (or undo one while rewinding) and
+    /// present the result. Returns the commands the input backend raised so
+    /// the caller can act on them.
+    pub fn frame(&mut self) -> Result<Vec<Command>, Chip8Error> {
+        let commands = self.input.poll(&mut self.chip8.keypad);
+        if commands.contains(&Command::Quit) {
+            return Ok(commands);
+        }
+        if commands.contains(&Command::Rewind) {
+            self.chip8.step_back(self.instructions_per_frame);
+            self.display.draw(&self.chip8.screen, self.chip8.width, self.chip8.height);
+            self.audio.update(false);
+            return Ok(commands);
+        }
+
+        if let Some(replay) = &mut self.replay {
+            replay.next(&mut self.chip8.keypad);
+        }
+        if let Some(movie) = &mut self.recording {
+            movie.frames.push(keypad_bits(&self.chip8.keypad));
+        }
+
+        self.chip8.run_frame(self.instructions_per_frame)?;
+        self.display.draw(&self.chip8.screen, self.chip8.width, self.chip8.height);
+        if let Some(pattern) = &self.chip8.audio_pattern {
+            self.audio.set_pattern(pattern, self.chip8.pitch);
+        }
+        self.audio.update(self.chip8.sound_timer > 0);
+
+        Ok(commands)
+    }
+
+    /// Keep the frontends alive without running the machine, e.g. while a
+    /// debugger has it stopped: poll input, redraw and silence the beeper.
+    pub fn idle(&mut self) -> Vec<Command> {
+        let commands = self.input.poll(&mut self.chip8.keypad);
+        self.display.draw(&self.chip8.screen, self.chip8.width, self.chip8.height);
+        self.audio.update(false);
+        commands
+    }
+}
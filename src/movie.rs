@@ -0,0 +1,225 @@
+//! Movies: the keypad input of a run, frame by frame, so it can be replayed
+//! exactly.
+//!
+//! Everything else a run depends on is recorded with it: a CRC-32 of the ROM,
+//! the platform, the quirks profile, the RNG seed and the speed. A movie
+//! always starts from a freshly loaded ROM and plays forwards: rewinding, or
+//! stopping in the debugger, changes where frames start in a way a replay
+//! can't reproduce, so frontends turn rewinding off while a movie is active.
+//!
+//! The file is plain text so it can be attached to a bug report:
+//!
+//! ```text
+//! chip8-movie 1
+//! rom 6E5A7A9F
+//! platform chip8
+//! quirks shift_vy=off,memory_increment=off,jump_vx=off,vf_reset=off,wrap_sprites=off,display_wait=off
+//! seed 1
+//! ips 700
+//! frames
+//! 0000*120
+//! 0010*8
+//! ```
+//!
+//! Each line after `frames` is the keypad for one frame as a hex bitmask (bit
+//! N is key N), with `*COUNT` for runs of identical frames.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::chip8::Chip8;
+use crate::error::Chip8Error;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::savestate::crc32;
+
+const HEADER: &str = "chip8-movie 1";
+
+/// The most frames a movie may hold, a day at 60 Hz, so a corrupt run
+/// length can't ask for more memory than there is.
+pub const MAX_FRAMES: usize = 60 * 60 * 60 * 24;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    /// CRC-32 of the ROM image.
+    pub rom: u32,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub seed: u64,
+    /// Instructions per second.
+    pub ips: u32,
+    /// Keypad bitmask for each frame.
+    pub frames: Vec<u16>,
+}
+
+impl Movie {
+    /// An empty movie for `rom` freshly loaded into `chip8`.
+    pub fn new(rom: &[u8], chip8: &Chip8, ips: u32) -> Movie {
+        Movie {
+            rom: crc32(rom),
+            platform: chip8.platform,
+            quirks: chip8.quirks,
+            seed: chip8.rng.seed,
+            ips,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Fail unless the movie was recorded with `rom`.
+    pub fn check_rom(&self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let crc = crc32(rom);
+        if crc != self.rom {
+            return Err(bad(&format!("recorded with another ROM (CRC-32 {:08X}, this one is {:08X})", self.rom, crc)));
+        }
+
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Movie, Chip8Error> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Chip8Error> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+}
+
+/// A movie being played back into the keypad.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub movie: Movie,
+    /// The next frame to play.
+    pub frame: usize,
+}
+
+impl Replay {
+    pub fn new(movie: Movie) -> Replay {
+        Replay { movie, frame: 0 }
+    }
+
+    /// Set `keypad` for the next frame. Returns false once the movie is over,
+    /// leaving `keypad` alone.
+    pub fn next(&mut self, keypad: &mut [bool]) -> bool {
+        match self.movie.frames.get(self.frame) {
+            Some(bits) => {
+                set_keypad(keypad, *bits);
+                self.frame += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.frame >= self.movie.frames.len()
+    }
+}
+
+/// One bit per key, bit N for key N.
+pub fn keypad_bits(keypad: &[bool]) -> u16 {
+    keypad.iter().enumerate().fold(0, |bits, (i, down)| bits | (*down as u16) << i)
+}
+
+pub fn set_keypad(keypad: &mut [bool], bits: u16) {
+    for (i, key) in keypad.iter_mut().enumerate() {
+        *key = bits & (1 << i) != 0;
+    }
+}
+
+fn bad(reason: &str) -> Chip8Error {
+    Chip8Error::BadMovie(reason.to_string())
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "rom {:08X}", self.rom)?;
+        writeln!(f, "platform {}", self.platform)?;
+        writeln!(f, "quirks {}", self.quirks)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "ips {}", self.ips)?;
+        writeln!(f, "frames")?;
+
+        let mut frames = self.frames.iter().peekable();
+        while let Some(bits) = frames.next() {
+            let mut count = 1;
+            while frames.peek() == Some(&bits) {
+                frames.next();
+                count += 1;
+            }
+
+            if count == 1 {
+                writeln!(f, "{:04X}", bits)?;
+            } else {
+                writeln!(f, "{:04X}*{}", bits, count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Movie {
+    type Err = Chip8Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err(bad("not a movie file")),
+        }
+
+        let (mut rom, mut platform, mut quirks, mut seed, mut ips) = (None, None, None, None, None);
+        for (number, line) in &mut lines {
+            if line == "frames" {
+                break;
+            }
+
+            let at = |reason: String| bad(&format!("line {}: {}", number, reason));
+            let (key, value) = match line.find(' ') {
+                Some(space) => (&line[..space], line[space + 1..].trim()),
+                None => return Err(at(format!("expected `KEY VALUE`, got `{}`", line))),
+            };
+            match key {
+                "rom" => rom = Some(u32::from_str_radix(value, 16).map_err(|_| at(format!("bad ROM CRC `{}`", value)))?),
+                "platform" => platform = Some(value.parse::<Platform>().map_err(at)?),
+                "quirks" => quirks = Some(value.parse::<Quirks>().map_err(at)?),
+                "seed" => seed = Some(value.parse::<u64>().map_err(|_| at(format!("bad seed `{}`", value)))?),
+                "ips" => ips = Some(value.parse::<u32>().map_err(|_| at(format!("bad speed `{}`", value)))?),
+                _ => return Err(at(format!("unknown key `{}`", key))),
+            }
+        }
+
+        let mut frames = Vec::new();
+        for (number, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            let (bits, count) = match line.find('*') {
+                Some(star) => (&line[..star], line[star + 1..].parse::<usize>().ok()),
+                None => (line, Some(1)),
+            };
+            match (u16::from_str_radix(bits, 16), count) {
+                (Ok(_), Some(count)) if count > MAX_FRAMES - frames.len() => {
+                    return Err(bad(&format!("line {}: more than {} frames", number, MAX_FRAMES)));
+                }
+                (Ok(bits), Some(count)) => frames.extend(std::iter::repeat_n(bits, count)),
+                _ => return Err(bad(&format!("line {}: bad frame `{}`", number, line))),
+            }
+        }
+
+        let missing = |key: &str| bad(&format!("missing `{}`", key));
+        Ok(Movie {
+            rom: rom.ok_or_else(|| missing("rom"))?,
+            platform: platform.ok_or_else(|| missing("platform"))?,
+            quirks: quirks.ok_or_else(|| missing("quirks"))?,
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            ips: ips.filter(|ips| *ips > 0).ok_or_else(|| missing("ips"))?,
+            frames,
+        })
+    }
+}
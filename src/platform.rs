@@ -0,0 +1,67 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::quirks::Quirks;
+
+/// Which instruction set the machine decodes. Extensions are only recognised
+/// on the platforms that introduced them; elsewhere they are unknown opcodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Chip8,
+    /// SUPER-CHIP 1.1: 128x64 hi-res mode, scrolling, 16x16 sprites, big font
+    /// and RPL user flags.
+    SuperChip,
+    /// XO-CHIP: everything SUPER-CHIP has plus 64 KiB of memory, two bitplanes
+    /// (four colours), audio patterns and a few extra instructions.
+    XoChip,
+}
+
+impl Platform {
+    /// The quirks ROMs written for this platform usually expect.
+    pub fn default_quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::modern(),
+            Platform::SuperChip => Quirks::schip(),
+            Platform::XoChip => Quirks::xochip(),
+        }
+    }
+
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::SuperChip => 4096,
+            Platform::XoChip => 65536,
+        }
+    }
+
+    /// How many RPL user flags `FX75`/`FX85` can address.
+    pub fn flag_count(self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::SuperChip => 8,
+            Platform::XoChip => 16,
+        }
+    }
+}
+
+/// The name `from_str` accepts.
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Platform::Chip8 => write!(f, "chip8"),
+            Platform::SuperChip => write!(f, "schip"),
+            Platform::XoChip => write!(f, "xochip"),
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chip8" => Ok(Platform::Chip8),
+            "schip" | "superchip" => Ok(Platform::SuperChip),
+            "xochip" => Ok(Platform::XoChip),
+            _ => Err(format!("unknown platform `{}` (expected chip8, schip or xochip)", s)),
+        }
+    }
+}
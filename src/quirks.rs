@@ -0,0 +1,168 @@
+//! Behaviours that differ between CHIP-8 interpreters. ROMs written for one
+//! platform often misbehave on another, so the machine takes a `Quirks`
+//! profile and `execute_opcode` consults it wherever implementations disagree.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8XY6`/`8XYE` shift Vy into Vx instead of shifting Vx in place.
+    pub shift_vy: bool,
+    /// `FX55`/`FX65` leave I pointing just past the last register touched.
+    pub memory_increment: bool,
+    /// `BXNN` jumps to XNN + Vx instead of `BNNN` jumping to NNN + V0.
+    pub jump_vx: bool,
+    /// `8XY1`/`8XY2`/`8XY3` reset VF to 0.
+    pub vf_reset: bool,
+    /// `DXYN` wraps pixels that fall off the edge of the screen around to the
+    /// other side instead of clipping them. Where a sprite starts always wraps.
+    pub wrap_sprites: bool,
+    /// `DXYN` waits for the next frame, so at most one sprite is drawn per frame.
+    pub display_wait: bool,
+}
+
+/// Names accepted by `Quirks::from_str`, in the order they are listed in help.
+pub const PRESETS: &[&str] = &["vip", "chip48", "schip", "xochip", "modern"];
+
+/// Flag names accepted by `Quirks::set`.
+pub const FLAGS: &[&str] = &["shift_vy", "memory_increment", "jump_vx", "vf_reset", "wrap_sprites", "display_wait"];
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::modern()
+    }
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub fn cosmac_vip() -> Quirks {
+        Quirks {
+            shift_vy: true,
+            memory_increment: true,
+            jump_vx: false,
+            vf_reset: true,
+            wrap_sprites: false,
+            display_wait: true,
+        }
+    }
+
+    /// CHIP-48 on the HP-48 calculators. Unlike SUPER-CHIP it still moves I
+    /// on `FX55`/`FX65`; the real thing moved it one less than the VIP did,
+    /// which `memory_increment` doesn't model.
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift_vy: false,
+            memory_increment: true,
+            jump_vx: true,
+            vf_reset: false,
+            wrap_sprites: false,
+            display_wait: false,
+        }
+    }
+
+    /// SUPER-CHIP 1.1.
+    pub fn schip() -> Quirks {
+        Quirks {
+            shift_vy: false,
+            memory_increment: false,
+            jump_vx: true,
+            vf_reset: false,
+            wrap_sprites: false,
+            display_wait: false,
+        }
+    }
+
+    /// XO-CHIP as implemented by Octo.
+    pub fn xochip() -> Quirks {
+        Quirks {
+            shift_vy: true,
+            memory_increment: true,
+            jump_vx: false,
+            vf_reset: false,
+            wrap_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    /// What most present-day interpreters and ROMs assume.
+    pub fn modern() -> Quirks {
+        Quirks {
+            shift_vy: false,
+            memory_increment: false,
+            jump_vx: false,
+            vf_reset: false,
+            wrap_sprites: false,
+            display_wait: false,
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Quirks> {
+        match name {
+            "vip" | "cosmac" => Some(Self::cosmac_vip()),
+            "chip48" => Some(Self::chip48()),
+            "schip" => Some(Self::schip()),
+            "xochip" => Some(Self::xochip()),
+            "modern" => Some(Self::modern()),
+            _ => None,
+        }
+    }
+
+    /// Override a single flag by name.
+    pub fn set(&mut self, flag: &str, value: bool) -> Result<(), String> {
+        match flag {
+            "shift_vy" => self.shift_vy = value,
+            "memory_increment" => self.memory_increment = value,
+            "jump_vx" => self.jump_vx = value,
+            "vf_reset" => self.vf_reset = value,
+            "wrap_sprites" => self.wrap_sprites = value,
+            "display_wait" => self.display_wait = value,
+            _ => return Err(format!("unknown quirk `{}` (expected one of {})", flag, FLAGS.join(", "))),
+        }
+
+        Ok(())
+    }
+}
+
+/// Every flag spelled out, e.g. `shift_vy=off,memory_increment=on,...`, which
+/// `from_str` reads back to the same profile.
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values = [self.shift_vy, self.memory_increment, self.jump_vx, self.vf_reset, self.wrap_sprites, self.display_wait];
+        for (i, (flag, value)) in FLAGS.iter().zip(values.iter()).enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(f, "{}{}={}", separator, flag, if *value { "on" } else { "off" })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses `PRESET[,FLAG=on|off...]`, e.g. `schip,shift_vy=on`. The preset may
+/// be left out to override flags on top of the modern profile.
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut quirks = Quirks::default();
+
+        for (i, part) in s.split(',').map(str::trim).enumerate() {
+            if let Some(eq) = part.find('=') {
+                let (flag, value) = (&part[..eq], &part[eq + 1..]);
+                let value = match value {
+                    "on" | "true" | "1" => true,
+                    "off" | "false" | "0" => false,
+                    _ => return Err(format!("bad value `{}` for quirk `{}` (expected on or off)", value, flag)),
+                };
+                quirks.set(flag, value)?;
+            } else if i == 0 {
+                quirks = Quirks::preset(part)
+                    .ok_or_else(|| format!("unknown quirks preset `{}` (expected one of {})", part, PRESETS.join(", ")))?;
+            } else {
+                return Err(format!("expected FLAG=on|off, got `{}`", part));
+            }
+        }
+
+        Ok(quirks)
+    }
+}
@@ -0,0 +1,197 @@
+//! Rewind: run the machine backwards.
+//!
+//! Every instruction is deterministic given the machine state and the two
+//! things the host changes between instructions, the keypad and the timers.
+//! `Rewind` records those inputs for every cycle and takes a full save state
+//! every `interval` cycles; any recent instruction is reconstructed by loading
+//! the nearest snapshot before it and replaying the recorded inputs.
+
+use std::collections::VecDeque;
+
+use crate::chip8::Chip8;
+use crate::movie::{keypad_bits, set_keypad};
+
+/// Cycles between snapshots.
+pub const DEFAULT_INTERVAL: usize = 100;
+/// Snapshots kept; with the default interval about 15 seconds at 700
+/// instructions per second.
+pub const DEFAULT_SNAPSHOTS: usize = 100;
+
+/// What the host had set when a cycle started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Input {
+    /// `Chip8::cycles` before the cycle ran.
+    cycles: usize,
+    /// One bit per hex key.
+    keypad: u16,
+    timer: u8,
+    sound_timer: u8,
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    /// Position in the input log of the first cycle after the snapshot.
+    position: u64,
+    state: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    interval: usize,
+    capacity: usize,
+    snapshots: VecDeque<Snapshot>,
+    inputs: VecDeque<Input>,
+    /// Position of `inputs[0]` since recording started.
+    first: u64,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(DEFAULT_SNAPSHOTS, DEFAULT_INTERVAL)
+    }
+}
+
+impl Rewind {
+    /// Keep up to `snapshots` snapshots taken every `interval` cycles. Room
+    /// for them is only made as they are taken.
+    pub fn new(snapshots: usize, interval: usize) -> Rewind {
+        Rewind {
+            interval: interval.max(1),
+            capacity: snapshots.max(1),
+            snapshots: VecDeque::new(),
+            inputs: VecDeque::new(),
+            first: 0,
+        }
+    }
+
+    /// Cycles that can currently be undone.
+    pub fn len(&self) -> usize {
+        match self.snapshots.front() {
+            Some(snapshot) => (self.end() - snapshot.position) as usize,
+            None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget everything, e.g. after the machine was replaced by a save state.
+    pub fn clear(&mut self) {
+        self.first = self.end();
+        self.snapshots.clear();
+        self.inputs.clear();
+    }
+
+    /// Called by `Chip8::cycle` before every cycle.
+    pub(crate) fn record(&mut self, chip8: &Chip8) {
+        let due = self.snapshots.back().is_none_or(|last| self.end() - last.position >= self.interval as u64);
+        if due {
+            self.snapshot(chip8);
+        }
+
+        let keypad = keypad_bits(&chip8.keypad);
+        self.inputs.push_back(Input { cycles: chip8.cycles, keypad, timer: chip8.timer, sound_timer: chip8.sound_timer });
+    }
+
+    /// Snapshot `chip8` as it is now, replacing one taken at the same point.
+    /// Needed whenever the machine is changed by something other than a
+    /// cycle, such as a debugger `set`, or replaying would lose the change.
+    pub(crate) fn snapshot(&mut self, chip8: &Chip8) {
+        let position = self.end();
+        if self.snapshots.back().is_some_and(|last| last.position == position) {
+            self.snapshots.pop_back();
+        }
+        self.snapshots.push_back(Snapshot { position, state: chip8.save_state() });
+
+        if self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+            if let Some(oldest) = self.snapshots.front() {
+                let stale = (oldest.position - self.first) as usize;
+                self.inputs.drain(..stale);
+                self.first = oldest.position;
+            }
+        }
+    }
+
+    fn end(&self) -> u64 {
+        self.first + self.inputs.len() as u64
+    }
+}
+
+impl Chip8 {
+    /// Go back `instructions` executed instructions, or as far as the rewind
+    /// buffer reaches. Returns how many instructions were undone; 0 when
+    /// rewinding is off or there is no history.
+    ///
+    /// Breakpoints and watchpoints are not checked while replaying, and trace
+    /// records for the undone instructions are dropped.
+    pub fn step_back(&mut self, instructions: usize) -> usize {
+        let mut rewind = match self.rewind.take() {
+            Some(rewind) => rewind,
+            None => return 0,
+        };
+
+        let start = self.cycles;
+        let undone = self.replay(&mut rewind, start.saturating_sub(instructions));
+        self.rewind = Some(rewind);
+        undone
+    }
+
+    /// Record the current state as a rewind point; see `Rewind::snapshot`.
+    pub fn checkpoint(&mut self) {
+        if let Some(mut rewind) = self.rewind.take() {
+            rewind.snapshot(self);
+            self.rewind = Some(rewind);
+        }
+    }
+
+    fn replay(&mut self, rewind: &mut Rewind, cycles: usize) -> usize {
+        let start = self.cycles;
+
+        // The first recorded cycle that starts with the target count, i.e. the
+        // state right after instruction `cycles` ran, or the oldest one if the
+        // history doesn't reach back that far.
+        let target = match rewind.inputs.iter().position(|input| input.cycles >= cycles) {
+            Some(i) => rewind.first + i as u64,
+            None => return 0,
+        };
+        let snapshot = match rewind.snapshots.iter().rev().find(|snapshot| snapshot.position <= target) {
+            Some(snapshot) => snapshot,
+            None => return 0,
+        };
+
+        let watchpoints = std::mem::take(&mut self.watchpoints);
+        let mut trace = self.trace.take();
+        // The snapshot was taken from this machine, so it always loads.
+        let _ = self.load_state(&snapshot.state);
+
+        for position in snapshot.position..=target {
+            let input = match rewind.inputs.get((position - rewind.first) as usize) {
+                Some(input) => *input,
+                None => break,
+            };
+            set_keypad(&mut self.keypad, input.keypad);
+            self.timer = input.timer;
+            self.sound_timer = input.sound_timer;
+
+            if position < target {
+                let _ = self.cycle();
+            }
+        }
+
+        self.watchpoints = watchpoints;
+        if let Some(trace) = &mut trace {
+            trace.forget_after(self.cycles);
+        }
+        self.trace = trace;
+
+        // Replaying from here on records the new future.
+        rewind.inputs.truncate((target - rewind.first) as usize);
+        while rewind.snapshots.back().is_some_and(|snapshot| snapshot.position > target) {
+            rewind.snapshots.pop_back();
+        }
+
+        start - self.cycles
+    }
+}
@@ -0,0 +1,33 @@
+/// The generator behind `CXNN`: SplitMix64, whose whole state is one
+/// number, so it is cheap to seed, save and restore with the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    pub state: u64,
+    /// What the generator started from; passing it to `new` repeats the
+    /// sequence.
+    pub seed: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed, seed }
+    }
+
+    /// A generator seeded from the operating system. The seed is kept, so
+    /// even an unseeded run can be reproduced.
+    pub fn from_entropy() -> Rng {
+        Rng::new(rand::random())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn byte(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
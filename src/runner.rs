@@ -0,0 +1,243 @@
+//! Running a ROM without any frontend, for scripts and CI.
+//!
+//! `run` executes frames until a frame limit or one of the `Until` conditions
+//! is reached, and the resulting `Report` dumps the registers, screen and
+//! memory as text or JSON.
+
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::chip8::Chip8;
+use crate::debugger::{hexdump, registers};
+use crate::error::Chip8Error;
+use crate::instruction::Instruction;
+
+/// A condition that ends a run, checked before every instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Until {
+    /// PC reaches the address.
+    Pc(u16),
+    /// The byte at `address` holds `value`.
+    Memory { address: u16, value: u8 },
+    /// The next instruction is a `1NNN` jump to itself, the usual way to
+    /// stop a CHIP-8 program.
+    SelfJump,
+}
+
+/// Why a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finish {
+    /// The frame limit ran out first.
+    Frames,
+    Pc(u16),
+    Memory { address: u16, value: u8 },
+    SelfJump(u16),
+    /// `00FD` stopped the machine.
+    Halted,
+}
+
+/// Which parts of the machine a report shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sections {
+    pub registers: bool,
+    pub screen: bool,
+    pub memory: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    pub finish: Finish,
+    /// Frames that ran, including a partly run last one.
+    pub frames: usize,
+}
+
+/// Frame limit when none is given: a minute at 60 Hz, so a ROM that never
+/// meets its `Until` condition can't hang a CI job.
+pub const DEFAULT_FRAMES: usize = 60 * 60;
+
+/// Run `chip8` for at most `frames` frames (`DEFAULT_FRAMES` if `None`)
+/// until one of `until` holds. Frames are run by `Chip8::run_frame_until`, so
+/// a run sees the same timers as it would in a window.
+pub fn run(chip8: &mut Chip8, instructions_per_frame: usize, frames: Option<usize>, until: &[Until]) -> Result<Report, Chip8Error> {
+    let limit = frames.unwrap_or(DEFAULT_FRAMES);
+    let mut finish = None;
+    for frame in 0..limit {
+        let mut ran = false;
+        chip8.run_frame_until(instructions_per_frame, |chip8| {
+            finish = reached(chip8, until);
+            ran |= finish.is_none();
+            finish.is_some()
+        })?;
+
+        if let Some(finish) = finish {
+            // A condition that already held when the frame started ends the
+            // run before it.
+            return Ok(Report { finish, frames: if ran { frame + 1 } else { frame } });
+        }
+    }
+
+    let finish = reached(chip8, until).unwrap_or(Finish::Frames);
+    Ok(Report { finish, frames: limit })
+}
+
+fn reached(chip8: &Chip8, until: &[Until]) -> Option<Finish> {
+    if chip8.halted {
+        return Some(Finish::Halted);
+    }
+
+    until.iter().find_map(|until| match *until {
+        Until::Pc(address) if chip8.pc == address => Some(Finish::Pc(address)),
+        Until::Memory { address, value } if chip8.memory.get(address as usize) == Some(&value) => {
+            Some(Finish::Memory { address, value })
+        }
+        Until::SelfJump => {
+            let instruction = chip8.memory.get(chip8.pc as usize..).and_then(|bytes| Instruction::decode(bytes, chip8.platform));
+            match instruction {
+                Some(Instruction::Jump(target)) if target == chip8.pc => Some(Finish::SelfJump(target)),
+                _ => None,
+            }
+        }
+        _ => None,
+    })
+}
+
+impl Report {
+    pub fn text(&self, chip8: &Chip8, sections: Sections) -> String {
+        let mut out = String::new();
+        writeln!(out, "{} after {} frames ({} instructions)", self.finish, self.frames, chip8.cycles).unwrap();
+
+        if sections.registers {
+            out.push('\n');
+            out.push_str(&registers(chip8));
+            let stack: Vec<String> = chip8.stack.iter().map(|address| format!("{:04X}", address)).collect();
+            writeln!(out, "stack: {}", if stack.is_empty() { "empty".to_string() } else { stack.join(" ") }).unwrap();
+        }
+
+        if sections.screen {
+            writeln!(out, "\nscreen {}x{}:", chip8.width, chip8.height).unwrap();
+            for row in screen_rows(chip8) {
+                writeln!(out, "{}", row).unwrap();
+            }
+        }
+
+        if sections.memory {
+            out.push_str("\nmemory:\n");
+            out.push_str(&hexdump(&chip8.memory, 0));
+        }
+
+        out
+    }
+
+    pub fn json(&self, chip8: &Chip8, sections: Sections) -> String {
+        let mut out = String::new();
+        out.push('{');
+        write!(out, "\"finish\":{{\"reason\":\"{}\"", self.finish.reason()).unwrap();
+        match self.finish {
+            Finish::Pc(address) | Finish::SelfJump(address) => write!(out, ",\"address\":{}", address).unwrap(),
+            Finish::Memory { address, value } => write!(out, ",\"address\":{},\"value\":{}", address, value).unwrap(),
+            Finish::Frames | Finish::Halted => {}
+        }
+        write!(out, "}},\"frames\":{},\"cycles\":{}", self.frames, chip8.cycles).unwrap();
+
+        if sections.registers {
+            write!(
+                out,
+                ",\"registers\":{{\"v\":{},\"i\":{},\"pc\":{},\"dt\":{},\"st\":{},\"stack\":{}}}",
+                list(chip8.registers.iter()),
+                chip8.index,
+                chip8.pc,
+                chip8.timer,
+                chip8.sound_timer,
+                list(chip8.stack.iter())
+            )
+            .unwrap();
+        }
+
+        if sections.screen {
+            let rows: Vec<String> = screen_rows(chip8).iter().map(|row| format!("\"{}\"", row)).collect();
+            write!(out, ",\"screen\":{{\"width\":{},\"height\":{},\"rows\":[{}]}}", chip8.width, chip8.height, rows.join(",")).unwrap();
+        }
+
+        if sections.memory {
+            let memory: String = chip8.memory.iter().map(|byte| format!("{:02X}", byte)).collect();
+            write!(out, ",\"memory\":\"{}\"", memory).unwrap();
+        }
+
+        out.push('}');
+        out
+    }
+}
+
+impl Finish {
+    /// Short name used in JSON reports.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Finish::Frames => "frames",
+            Finish::Pc(_) => "pc",
+            Finish::Memory { .. } => "memory",
+            Finish::SelfJump(_) => "self_jump",
+            Finish::Halted => "halted",
+        }
+    }
+}
+
+/// One string per row: `.` for unlit pixels, `#` for plane 1 and the plane
+/// mask digit for XO-CHIP's other colours.
+pub fn screen_rows(chip8: &Chip8) -> Vec<String> {
+    chip8
+        .screen
+        .chunks(chip8.width)
+        .map(|row| {
+            row.iter()
+                .map(|pixel| match pixel {
+                    0 => '.',
+                    1 => '#',
+                    other => std::char::from_digit(*other as u32 & 0b11, 10).unwrap_or('?'),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn list<'a, T: fmt::Display + 'a>(values: impl Iterator<Item = &'a T>) -> String {
+    let values: Vec<String> = values.map(T::to_string).collect();
+    format!("[{}]", values.join(","))
+}
+
+impl fmt::Display for Finish {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Finish::Frames => write!(f, "frame limit reached"),
+            Finish::Pc(address) => write!(f, "PC reached {:04X}", address),
+            Finish::Memory { address, value } => write!(f, "[{:04X}] holds {:02X}", address, value),
+            Finish::SelfJump(address) => write!(f, "jump to itself at {:04X}", address),
+            Finish::Halted => write!(f, "halted"),
+        }
+    }
+}
+
+impl Default for Sections {
+    fn default() -> Self {
+        Sections { registers: true, screen: true, memory: true }
+    }
+}
+
+/// A comma-separated subset of `registers`, `screen` and `memory`.
+impl FromStr for Sections {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut sections = Sections { registers: false, screen: false, memory: false };
+        for part in s.split(',').map(str::trim) {
+            match part {
+                "registers" | "regs" => sections.registers = true,
+                "screen" => sections.screen = true,
+                "memory" | "mem" => sections.memory = true,
+                _ => return Err(format!("unknown dump section `{}` (expected registers, screen or memory)", part)),
+            }
+        }
+
+        Ok(sections)
+    }
+}
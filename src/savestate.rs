@@ -0,0 +1,299 @@
+//! Save states: the whole machine in a small binary file.
+//!
+//! A state is the magic bytes `C8SS`, a little-endian `u16` version, the
+//! fields below in order and a CRC-32 of everything before it. Integers are
+//! little-endian and variable-length fields are prefixed with their length.
+//! Debugging aids (trace, breakpoints, watchpoints) are not machine state and
+//! are left alone by `load_state`, except that the rewind history no longer
+//! applies and is cleared.
+
+use std::fs;
+use std::path::Path;
+
+use crate::chip8::{Chip8, KeyWait, HIRES_HEIGHT, HIRES_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::error::Chip8Error;
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::rng::Rng;
+
+pub const MAGIC: &[u8; 4] = b"C8SS";
+pub const VERSION: u16 = 2;
+
+impl Chip8 {
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Writer(Vec::with_capacity(self.memory.len() + self.screen.len() + 128));
+        out.bytes(MAGIC);
+        out.u16(VERSION);
+
+        out.u8(match self.platform {
+            Platform::Chip8 => 0,
+            Platform::SuperChip => 1,
+            Platform::XoChip => 2,
+        });
+        out.u8(quirk_bits(&self.quirks));
+
+        out.bytes(&self.registers);
+        out.u16(self.index);
+        out.u16(self.pc);
+        out.u8(self.stack.len() as u8);
+        for address in &self.stack {
+            out.u16(*address);
+        }
+        out.u8(self.timer);
+        out.u8(self.sound_timer);
+        out.u64(self.cycles as u64);
+
+        for key in &self.keypad {
+            out.u8(*key as u8);
+        }
+        match self.key_wait {
+            Some(KeyWait { register, pressed }) => {
+                out.u8(1);
+                out.u8(register);
+                out.u8(pressed.unwrap_or(0xFF));
+            }
+            None => out.bytes(&[0, 0, 0]),
+        }
+
+        out.u32(self.memory.len() as u32);
+        out.bytes(&self.memory);
+        out.u8(self.hires as u8);
+        out.u8(self.planes);
+        out.bytes(&self.screen);
+
+        match &self.audio_pattern {
+            Some(pattern) => {
+                out.u8(1);
+                out.bytes(pattern);
+            }
+            None => {
+                out.u8(0);
+                out.bytes(&[0; 16]);
+            }
+        }
+        out.u8(self.pitch);
+        out.u8(self.flags.len() as u8);
+        out.bytes(&self.flags);
+        out.u8(self.halted as u8);
+        out.u64(self.rng.seed);
+        out.u64(self.rng.state);
+
+        let checksum = crc32(&out.0);
+        out.u32(checksum);
+        out.0
+    }
+
+    /// Replace the machine state with a saved one. Nothing changes unless the
+    /// whole state is valid.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+        if data.len() < MAGIC.len() + 2 + 4 || &data[..MAGIC.len()] != MAGIC {
+            return Err(bad("not a save state"));
+        }
+
+        let (body, checksum) = data.split_at(data.len() - 4);
+        let mut input = Reader { data: body, position: MAGIC.len() };
+        let version = input.u16()?;
+        if version != VERSION {
+            return Err(bad(&format!("version {} is not supported (expected {})", version, VERSION)));
+        }
+        if crc32(body).to_le_bytes() != checksum {
+            return Err(bad("checksum mismatch"));
+        }
+
+        let platform = match input.u8()? {
+            0 => Platform::Chip8,
+            1 => Platform::SuperChip,
+            2 => Platform::XoChip,
+            other => return Err(bad(&format!("unknown platform {}", other))),
+        };
+        let quirks = quirks_from_bits(input.u8()?);
+
+        let registers = input.bytes(16)?.to_vec();
+        let index = input.u16()?;
+        let pc = input.u16()?;
+        let depth = input.u8()? as usize;
+        if depth > 16 {
+            return Err(bad("stack is too deep"));
+        }
+        let mut stack = Vec::with_capacity(16);
+        for _ in 0..depth {
+            stack.push(input.u16()?);
+        }
+        let timer = input.u8()?;
+        let sound_timer = input.u8()?;
+        let cycles = input.u64()? as usize;
+
+        let keypad: Vec<bool> = input.bytes(16)?.iter().map(|key| *key != 0).collect();
+        let (waiting, register, pressed) = (input.u8()?, input.u8()?, input.u8()?);
+        let key_wait = if waiting == 0 {
+            None
+        } else {
+            Some(KeyWait { register: register & 0xF, pressed: if pressed == 0xFF { None } else { Some(pressed & 0xF) } })
+        };
+
+        let memory_size = input.u32()? as usize;
+        if memory_size != platform.memory_size() {
+            return Err(bad("memory size does not match the platform"));
+        }
+        let memory = input.bytes(memory_size)?.to_vec();
+        let hires = input.u8()? != 0;
+        let planes = input.u8()? & 0b11;
+        let (width, height) = if hires { (HIRES_WIDTH, HIRES_HEIGHT) } else { (SCREEN_WIDTH, SCREEN_HEIGHT) };
+        let screen = input.bytes(width * height)?.to_vec();
+
+        let has_pattern = input.u8()? != 0;
+        let mut pattern = [0; 16];
+        pattern.copy_from_slice(input.bytes(16)?);
+        let pitch = input.u8()?;
+        let flag_count = input.u8()? as usize;
+        if flag_count != platform.flag_count() {
+            return Err(bad("flag count does not match the platform"));
+        }
+        let flags = input.bytes(flag_count)?.to_vec();
+        let halted = input.u8()? != 0;
+        let seed = input.u64()?;
+        let rng = Rng { state: input.u64()?, seed };
+
+        if input.position != body.len() {
+            return Err(bad("unexpected data at the end"));
+        }
+
+        self.platform = platform;
+        self.quirks = quirks;
+        self.registers = registers;
+        self.index = index;
+        self.pc = pc;
+        self.stack = stack;
+        self.timer = timer;
+        self.sound_timer = sound_timer;
+        self.cycles = cycles;
+        self.keypad = keypad;
+        self.key_wait = key_wait;
+        self.memory = memory;
+        self.hires = hires;
+        self.width = width;
+        self.height = height;
+        self.planes = planes;
+        self.screen = screen;
+        self.audio_pattern = if has_pattern { Some(pattern) } else { None };
+        self.pitch = pitch;
+        self.flags = flags;
+        self.halted = halted;
+        self.rng = rng;
+        self.stop = None;
+        self.vblank_wait = false;
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
+        }
+
+        Ok(())
+    }
+}
+
+/// Write a save state of `chip8` to `path`.
+pub fn write(chip8: &Chip8, path: &Path) -> Result<(), Chip8Error> {
+    fs::write(path, chip8.save_state())?;
+    Ok(())
+}
+
+/// Restore `chip8` from the save state at `path`.
+pub fn read(chip8: &mut Chip8, path: &Path) -> Result<(), Chip8Error> {
+    let data = fs::read(path)?;
+    chip8.load_state(&data)
+}
+
+fn bad(reason: &str) -> Chip8Error {
+    Chip8Error::BadSaveState(reason.to_string())
+}
+
+fn quirk_bits(quirks: &Quirks) -> u8 {
+    [quirks.shift_vy, quirks.memory_increment, quirks.jump_vx, quirks.vf_reset, quirks.wrap_sprites, quirks.display_wait]
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, set)| bits | (*set as u8) << i)
+}
+
+fn quirks_from_bits(bits: u8) -> Quirks {
+    let bit = |i: u8| bits & (1 << i) != 0;
+    Quirks {
+        shift_vy: bit(0),
+        memory_increment: bit(1),
+        jump_vx: bit(2),
+        vf_reset: bit(3),
+        wrap_sprites: bit(4),
+        display_wait: bit(5),
+    }
+}
+
+/// CRC-32 as used by zip and PNG.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+        let end = self.position.checked_add(len).filter(|end| *end <= self.data.len()).ok_or_else(|| bad("truncated"))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Chip8Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Chip8Error> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.bytes(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u32(&mut self) -> Result<u32, Chip8Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, Chip8Error> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
@@ -0,0 +1,157 @@
+//! Instruction tracing. While `Chip8::trace` is set every executed
+//! instruction leaves a `TraceRecord` in a fixed-size ring buffer; the oldest
+//! records are dropped as new ones arrive, and nothing is formatted until a
+//! record is displayed.
+
+use std::collections::vec_deque::{self, VecDeque};
+use std::fmt;
+
+use crate::instruction::Instruction;
+
+/// How many records a trace keeps unless told otherwise.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// The state an instruction saw and left behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceRecord {
+    /// Value of `Chip8::cycles` once the instruction was fetched.
+    pub cycle: usize,
+    pub pc: u16,
+    pub opcode: u16,
+    /// `None` when the opcode is not an instruction on the running platform.
+    pub instruction: Option<Instruction>,
+    pub registers_before: [u8; 16],
+    pub registers_after: [u8; 16],
+    pub index_before: u16,
+    pub index_after: u16,
+}
+
+/// A register an instruction changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delta {
+    pub register: Register,
+    pub before: u16,
+    pub after: u16,
+}
+
+impl TraceRecord {
+    /// The registers whose value differs after the instruction, V0 to VF
+    /// then I.
+    pub fn deltas(&self) -> impl Iterator<Item = Delta> + '_ {
+        let registers = (0..16).map(move |i| Delta {
+            register: Register::V(i as u8),
+            before: self.registers_before[i] as u16,
+            after: self.registers_after[i] as u16,
+        });
+        let index = Delta { register: Register::I, before: self.index_before, after: self.index_after };
+
+        registers.chain(Some(index)).filter(|delta| delta.before != delta.after)
+    }
+}
+
+impl fmt::Display for Delta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.register {
+            Register::V(i) => write!(f, "V{:X} {:02X}->{:02X}", i, self.before, self.after),
+            Register::I => write!(f, "I {:04X}->{:04X}", self.before, self.after),
+        }
+    }
+}
+
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let instruction = match self.instruction {
+            Some(instruction) => instruction.to_string(),
+            None => "???".to_string(),
+        };
+
+        write!(f, "{:>8}  {:04X}: {:04X}  ", self.cycle, self.pc, self.opcode)?;
+        let mut deltas = self.deltas().peekable();
+        if deltas.peek().is_none() {
+            return write!(f, "{}", instruction);
+        }
+
+        write!(f, "{:<20}", instruction)?;
+        for delta in deltas {
+            write!(f, " {}", delta)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fixed-capacity ring buffer of the most recent `TraceRecord`s.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    records: VecDeque<TraceRecord>,
+    capacity: usize,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Trace {
+    pub fn new(capacity: usize) -> Trace {
+        Trace { records: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Add a record, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, record: TraceRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+
+        self.records.push_back(record);
+    }
+
+    /// Records from oldest to newest.
+    pub fn iter(&self) -> vec_deque::Iter<'_, TraceRecord> {
+        self.records.iter()
+    }
+
+    /// The most recent record.
+    pub fn last(&self) -> Option<&TraceRecord> {
+        self.records.back()
+    }
+
+    /// Remove and return every record, oldest first.
+    pub fn drain(&mut self) -> vec_deque::Drain<'_, TraceRecord> {
+        self.records.drain(..)
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// Drop the records of instructions after `cycle`, e.g. after rewinding.
+    pub fn forget_after(&mut self, cycle: usize) {
+        while self.records.back().is_some_and(|record| record.cycle > cycle) {
+            self.records.pop_back();
+        }
+    }
+}
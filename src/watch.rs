@@ -0,0 +1,148 @@
+//! Watchpoints: stop the machine as soon as an instruction touches a piece
+//! of memory or changes a register, and say which instruction it was.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// What a watchpoint looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Memory addresses `start..=end`.
+    Memory { start: u16, end: u16 },
+    V(u8),
+    I,
+    Delay,
+    Sound,
+}
+
+/// Which memory accesses trigger a watchpoint. Register watchpoints trigger
+/// when an instruction changes the value, whatever the access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// A write that stores a different value from the one already there.
+    Change,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub target: Target,
+    pub access: Access,
+}
+
+/// A triggered watchpoint, as reported by `Stop::Watchpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Index into `Chip8::watchpoints`.
+    pub watchpoint: usize,
+    pub access: Access,
+    /// The address touched, for memory watchpoints.
+    pub address: Option<u16>,
+    pub old: u16,
+    pub new: u16,
+    /// Where the instruction that triggered it lives, and its opcode.
+    pub pc: u16,
+    pub opcode: u16,
+}
+
+impl Watchpoint {
+    /// Whether a memory access at `address` that found `old` and left `new`
+    /// triggers this watchpoint.
+    pub fn matches_memory(&self, address: u16, access: Access, old: u8, new: u8) -> bool {
+        let inside = match self.target {
+            Target::Memory { start, end } => start <= address && address <= end,
+            _ => false,
+        };
+
+        inside && match (self.access, access) {
+            (Access::Read, Access::Read) | (Access::Write, Access::Write) => true,
+            (Access::Change, Access::Write) => old != new,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Target::Memory { start, end } if start == end => write!(f, "{:04X}", start),
+            Target::Memory { start, end } => write!(f, "{:04X}-{:04X}", start, end),
+            Target::V(x) => write!(f, "V{:X}", x),
+            Target::I => write!(f, "I"),
+            Target::Delay => write!(f, "DT"),
+            Target::Sound => write!(f, "ST"),
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Access::Read => write!(f, "read"),
+            Access::Write => write!(f, "write"),
+            Access::Change => write!(f, "change"),
+        }
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.target {
+            Target::Memory { .. } => write!(f, "{} {}", self.target, self.access),
+            _ => write!(f, "{} change", self.target),
+        }
+    }
+}
+
+/// `V0`-`VF`, `I`, `DT`, `ST`, or a hex address or inclusive `START-END`
+/// range.
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        match upper.as_str() {
+            "I" => return Ok(Target::I),
+            "DT" => return Ok(Target::Delay),
+            "ST" => return Ok(Target::Sound),
+            _ => {}
+        }
+
+        let mut chars = upper.chars();
+        if let (Some('V'), Some(digit), None) = (chars.next(), chars.next(), chars.next()) {
+            if let Some(x) = digit.to_digit(16) {
+                return Ok(Target::V(x as u8));
+            }
+        }
+
+        let address = |text: &str| {
+            let digits = text.trim().trim_start_matches("0X");
+            u16::from_str_radix(digits, 16).map_err(|_| format!("bad watchpoint target `{}`", s))
+        };
+
+        let (start, end) = match upper.find('-') {
+            Some(dash) => (address(&upper[..dash])?, address(&upper[dash + 1..])?),
+            None => (address(&upper)?, address(&upper)?),
+        };
+
+        if end < start {
+            return Err(format!("watchpoint range `{}` ends before it starts", s));
+        }
+
+        Ok(Target::Memory { start, end })
+    }
+}
+
+impl FromStr for Access {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" | "r" => Ok(Access::Read),
+            "write" | "w" => Ok(Access::Write),
+            "change" | "c" => Ok(Access::Change),
+            _ => Err(format!("unknown access `{}` (expected read, write or change)", s)),
+        }
+    }
+}
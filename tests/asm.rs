@@ -0,0 +1,178 @@
+//! The assembler: every mnemonic round trip, directives and symbols, and
+//! where errors are reported.
+
+use std::fs;
+use std::path::Path;
+
+use chip8::asm::{assemble, assemble_file, AsmError};
+use chip8::{Instruction, Platform};
+
+/// One of each instruction, with operands that fill their fields.
+fn every_instruction() -> Vec<Instruction> {
+    use chip8::Instruction::*;
+
+    vec![
+        Cls, Ret, ScrollDown(0xA), ScrollUp(0x3), ScrollRight, ScrollLeft, Exit, Low, High,
+        Jump(0xABC), Call(0x123), SkipEqByte(0x1, 0xFF), SkipNeByte(0xE, 0x00), SkipEq(0x2, 0x3),
+        StoreRange(0x4, 0x1), LoadRange(0x1, 0x4), LoadByte(0xF, 0x7F), AddByte(0x0, 0x80),
+        Move(0xA, 0xB), Or(0x1, 0x2), And(0x3, 0x4), Xor(0x5, 0x6), Add(0x7, 0x8), Sub(0x9, 0xA),
+        Shr(0xB, 0xC), SubN(0xD, 0xE), Shl(0xF, 0x0), SkipNe(0x1, 0x1), LoadIndex(0xFFF),
+        JumpOffset(0x200), Random(0x3, 0x0F), Draw(0x4, 0x5, 0xF), SkipKey(0x6), SkipNoKey(0x7),
+        GetDelay(0x8), WaitKey(0x9), SetDelay(0xA), SetSound(0xB), AddIndex(0xC), Font(0xD),
+        BigFont(0xE), Bcd(0xF), Store(0x5), Load(0x6), StoreFlags(0x7), LoadFlags(0x3),
+        LoadLongIndex(0xBEEF), Plane(0x3), Audio, Pitch(0x2),
+    ]
+}
+
+/// Fails to compile when an instruction is added, as a reminder to add it
+/// to `every_instruction`.
+#[allow(dead_code)]
+fn listed(instruction: Instruction) {
+    use chip8::Instruction::*;
+
+    match instruction {
+        Cls | Ret | ScrollDown(_) | ScrollUp(_) | ScrollRight | ScrollLeft | Exit | Low | High | Jump(_) | Call(_)
+        | SkipEqByte(..) | SkipNeByte(..) | SkipEq(..) | StoreRange(..) | LoadRange(..) | LoadByte(..) | AddByte(..)
+        | Move(..) | Or(..) | And(..) | Xor(..) | Add(..) | Sub(..) | Shr(..) | SubN(..) | Shl(..) | SkipNe(..)
+        | LoadIndex(_) | JumpOffset(_) | Random(..) | Draw(..) | SkipKey(_) | SkipNoKey(_) | GetDelay(_) | WaitKey(_)
+        | SetDelay(_) | SetSound(_) | AddIndex(_) | Font(_) | BigFont(_) | Bcd(_) | Store(_) | Load(_)
+        | StoreFlags(_) | LoadFlags(_) | LoadLongIndex(_) | Plane(_) | Audio | Pitch(_) => {}
+    }
+}
+
+fn error(source: &str) -> AsmError {
+    match assemble(source, Platform::XoChip) {
+        Ok(rom) => panic!("expected `{}` to fail, got {:02X?}", source, rom),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn every_mnemonic_round_trips() {
+    for instruction in every_instruction() {
+        let text = instruction.to_string();
+        let rom = assemble(&text, Platform::XoChip).unwrap_or_else(|err| panic!("`{}`: {}", text, err));
+        assert_eq!(rom, instruction.encode(), "`{}`", text);
+        assert_eq!(Instruction::decode(&rom, Platform::XoChip), Some(instruction), "`{}`", text);
+    }
+}
+
+#[test]
+fn mnemonics_and_registers_ignore_case() {
+    assert_eq!(assemble("mov v1, 2\ndrw V1, v2, 3", Platform::Chip8).unwrap(), vec![0x61, 0x02, 0xD1, 0x23]);
+}
+
+#[test]
+fn one_operand_shifts_shift_in_place() {
+    assert_eq!(assemble("SHR V3\nSHL V4", Platform::Chip8).unwrap(), vec![0x83, 0x36, 0x84, 0x4E]);
+}
+
+#[test]
+fn labels_resolve_forwards_and_backwards() {
+    let source = "
+start:  CALL sub
+        JUMP start
+sub:    RET
+";
+    assert_eq!(assemble(source, Platform::Chip8).unwrap(), vec![0x22, 0x04, 0x12, 0x00, 0x00, 0xEE]);
+}
+
+#[test]
+fn constants_and_expressions() {
+    let source = "
+BASE = table - 2
+COUNT = 3 + $10 - %1
+        MOV V0, COUNT
+        MOV I, BASE + 2
+table:  db 0
+";
+    assert_eq!(assemble(source, Platform::Chip8).unwrap(), vec![0x60, 0x12, 0xA2, 0x04, 0x00]);
+}
+
+#[test]
+fn any_whitespace_separates_terms() {
+    assert_eq!(assemble("MOV V0, 1 +\u{a0}2\u{2003}- 1", Platform::Chip8).unwrap(), vec![0x60, 0x02]);
+}
+
+#[test]
+fn data_directives() {
+    let source = "
+        db 1, 0xFF, -1, $10
+        dw 0x1234, here
+here:   db %.##....., %#......#
+";
+    assert_eq!(
+        assemble(source, Platform::Chip8).unwrap(),
+        vec![0x01, 0xFF, 0xFF, 0x10, 0x12, 0x34, 0x02, 0x08, 0x60, 0x81]
+    );
+}
+
+#[test]
+fn long_index_takes_four_bytes() {
+    let source = "
+        MOV I, LONG data
+        JUMP data
+data:   db 7
+";
+    assert_eq!(assemble(source, Platform::XoChip).unwrap(), vec![0xF0, 0x00, 0x02, 0x06, 0x12, 0x06, 0x07]);
+}
+
+#[test]
+fn includes_are_relative_to_the_including_file() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("asm-include");
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("main.asm"), "        CALL draw\n        include \"lib/draw.asm\"\n").unwrap();
+    fs::write(dir.join("lib/draw.asm"), "draw:   include \"sprite.asm\"\n        RET\n").unwrap();
+    fs::write(dir.join("lib/sprite.asm"), "SIZE = 5\n        MOV V0, SIZE\n").unwrap();
+
+    let rom = assemble_file(&dir.join("main.asm"), Platform::Chip8).unwrap();
+    assert_eq!(rom, vec![0x22, 0x02, 0x60, 0x05, 0x00, 0xEE]);
+}
+
+#[test]
+fn errors_in_includes_name_the_file() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("asm-include-error");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.asm"), "CLS\ninclude \"bad.asm\"\n").unwrap();
+    fs::write(dir.join("bad.asm"), "\n  MOV V0, nowhere\n").unwrap();
+
+    let err = assemble_file(&dir.join("main.asm"), Platform::Chip8).unwrap_err();
+    assert!(err.file.as_deref().is_some_and(|file| file.ends_with("bad.asm")), "{}", err);
+    assert_eq!((err.line, err.column), (2, 11));
+    assert_eq!(err.message, "undefined name `nowhere`");
+}
+
+#[test]
+fn error_positions() {
+    // (source, line, column, message)
+    let cases = [
+        ("CLS\n  FOO V1", 2, 3, "unknown instruction `FOO`"),
+        ("  MOV V1", 1, 3, "bad operands for `MOV`"),
+        ("MOV V1, 0x100", 1, 9, "256 does not fit in a byte"),
+        ("JUMP  missing", 1, 7, "undefined name `missing`"),
+        ("DRW V0, V1, 16", 1, 13, "16 does not fit in a nibble"),
+        ("x: CLS\nx: RET", 2, 1, "`x` is already defined"),
+        ("  V1 = 3", 1, 3, "`V1` is reserved and can't be used as a name"),
+        ("MOV V0, 1 +", 1, 12, "expected a value"),
+        ("MOV V0, 1 2", 1, 11, "expected `+` or `-`, found `2`"),
+        ("MOV V0, , 1", 1, 9, "missing operand"),
+        ("db", 1, 1, "`db` needs at least one value"),
+        ("A = B\nB = A\nMOV V0, A", 2, 5, "`A` is defined in terms of itself"),
+    ];
+
+    for (source, line, column, message) in cases.iter() {
+        let err = error(source);
+        assert_eq!((err.line, err.column, err.message.as_str()), (*line, *column, *message), "`{}`", source);
+        assert_eq!(err.file, None);
+    }
+}
+
+#[test]
+fn extensions_need_their_platform() {
+    let err = assemble("CLS\n  HIGH", Platform::Chip8).unwrap_err();
+    assert_eq!((err.line, err.column), (2, 3));
+    assert_eq!(err.message, "`HIGH` is not available on the chip8 platform");
+    let err = assemble("PLANE 1", Platform::SuperChip).unwrap_err();
+    assert_eq!(err.message, "`PLANE 1` is not available on the schip platform");
+    assert!(assemble("HIGH", Platform::SuperChip).is_ok());
+}
@@ -0,0 +1,53 @@
+//! Options the `chip8` binary refuses before it loads anything.
+
+use std::process::{Command, Output};
+
+fn chip8(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chip8")).args(args).output().unwrap()
+}
+
+fn refused(args: &[&str]) -> String {
+    let output = chip8(args);
+    assert_eq!(output.status.code(), Some(2), "{:?}", args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr.lines().next().unwrap_or("").to_string()
+}
+
+#[test]
+fn movies_refuse_the_debugger() {
+    for movie in ["--record", "--replay"] {
+        for debug in [&["--debug"][..], &["--break", "200"]] {
+            let mut args = vec![movie, "game.movie"];
+            args.extend_from_slice(debug);
+            args.push("game.ch8");
+            assert_eq!(
+                refused(&args),
+                "chip8: movies can't be used with --debug or --break, which change where frames start",
+                "{:?}",
+                args
+            );
+        }
+    }
+}
+
+#[test]
+fn movies_refuse_save_states() {
+    for movie in ["--record", "--replay"] {
+        let args = [movie, "game.movie", "--load-state", "game.state", "game.ch8"];
+        assert_eq!(refused(&args), "chip8: movies start from a fresh machine, not --load-state");
+    }
+}
+
+#[test]
+fn rewind_is_bounded() {
+    for seconds in ["121", "4000000000"] {
+        assert_eq!(refused(&["--rewind", seconds, "game.ch8"]), "chip8: --rewind can be at most 120 seconds");
+    }
+}
+
+#[test]
+fn beep_is_an_audible_frequency() {
+    for frequency in ["NaN", "inf", "0", "-440", "19.5", "20001"] {
+        assert_eq!(refused(&["--beep", frequency, "game.ch8"]), "chip8: --beep must be between 20 and 20000 Hz");
+    }
+}
@@ -0,0 +1,26 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+
+use chip8::{Chip8, Chip8Error};
+
+/// A ROM image of `program`, one opcode per two bytes.
+pub fn rom(program: &[u16]) -> Vec<u8> {
+    program.iter().flat_map(|opcode| opcode.to_be_bytes()).collect()
+}
+
+/// `chip8` with `program` loaded and about to run.
+pub fn load(mut chip8: Chip8, program: &[u16]) -> Chip8 {
+    chip8.load_rom(&rom(program)).unwrap();
+    chip8
+}
+
+/// The error `result` failed with, as it is shown to the user.
+pub fn error<T: Debug>(result: Result<T, Chip8Error>) -> String {
+    match result {
+        Ok(value) => panic!("expected an error, got {:?}", value),
+        Err(err) => err.to_string(),
+    }
+}
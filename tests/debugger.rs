@@ -0,0 +1,60 @@
+//! Debugger commands driven against a small program.
+
+mod common;
+
+use chip8::debugger::{Debugger, Outcome};
+use chip8::Chip8;
+
+fn machine(program: &[u16]) -> Chip8 {
+    common::load(Chip8::new(), program)
+}
+
+#[test]
+fn step_stops_at_a_watchpoint() {
+    let mut chip8 = machine(&[0x6001, 0x6102, 0x6003, 0x6004]);
+    let mut debugger = Debugger::new();
+    debugger.execute(&mut chip8, "wp V1");
+
+    let (out, outcome) = debugger.execute(&mut chip8, "step 4");
+    assert_eq!(outcome, Outcome::Paused);
+    assert_eq!(chip8.pc, 0x204, "{}", out);
+    assert!(out.contains("V1"), "{}", out);
+    // The hit is reported once, not again on the next continue.
+    assert!(chip8.stop.is_none());
+}
+
+#[test]
+fn step_stops_at_a_breakpoint() {
+    let mut chip8 = machine(&[0x6001, 0x6102, 0x6003, 0x6004]);
+    let mut debugger = Debugger::new();
+    debugger.execute(&mut chip8, "break 204");
+
+    let (out, _) = debugger.execute(&mut chip8, "step 4");
+    assert_eq!(chip8.pc, 0x204);
+    assert!(out.starts_with("breakpoint at 0204"), "{}", out);
+    assert!(chip8.stop.is_none());
+
+    // Stepping from the breakpoint itself runs the instruction there.
+    debugger.execute(&mut chip8, "step");
+    assert_eq!(chip8.pc, 0x206);
+}
+
+#[test]
+fn continue_stops_at_a_watchpoint_on_the_breakpoint() {
+    let mut chip8 = machine(&[0x6001, 0x6102, 0x6003, 0x6004]);
+    let mut debugger = Debugger::new();
+    debugger.execute(&mut chip8, "break 202");
+    debugger.execute(&mut chip8, "wp V1");
+
+    chip8.run_frame(10).unwrap();
+    assert_eq!(chip8.pc, 0x202);
+    debugger.stopped(&mut chip8);
+
+    // Stepping off the breakpoint writes V1 and stops right after it.
+    let (out, outcome) = debugger.execute(&mut chip8, "continue");
+    assert_eq!(outcome, Outcome::Paused);
+    assert_eq!(chip8.pc, 0x204, "{}", out);
+    assert!(out.contains("V1"), "{}", out);
+    assert!(out.contains("=> "), "{}", out);
+    assert!(chip8.stop.is_none());
+}
@@ -0,0 +1,84 @@
+//! Listings of a small hand-assembled ROM: code reached by jumps, calls and skips,
+//! data nothing reaches, and generated labels.
+
+use chip8::disasm::disassemble;
+use chip8::Platform;
+
+/// Assembled from:
+///
+/// ```text
+/// start:  CALL draw
+///         JE V0, 1
+///         JUMP start
+///         JUMP done
+/// table:  db 0xFF, 0x12
+/// draw:   MOV I, sprite
+///         DRW V0, V1, 2
+///         RET
+/// sprite: db %##......, %.##.....
+/// done:   JNE V2, 3
+///         MOV I, LONG sprite
+///         JUMP done
+/// ```
+const ROM: &[u8] = &[
+    0x22, 0x0A, 0x30, 0x01, 0x12, 0x00, 0x12, 0x12, 0xFF, 0x12, 0xA2, 0x10, 0xD0, 0x12, 0x00, 0xEE, 0xC0, 0x60, 0x42,
+    0x03, 0xF0, 0x00, 0x02, 0x10, 0x12, 0x12,
+];
+
+#[test]
+fn listing_separates_code_from_data() {
+    let listing = disassemble(ROM, Platform::XoChip);
+
+    let expected = "\
+L200:
+0200  220A              CALL S20A
+0202  3001              JE V0, 0x01
+0204  1200              JUMP L200
+0206  1212              JUMP L212
+0208  FF12              db 0xFF, 0x12
+S20A:
+020A  A210              MOV I, 0x210
+020C  D012              DRW V0, V1, 2
+020E  00EE              RET
+0210  C060              db 0xC0, 0x60
+L212:
+0212  4203              JNE V2, 0x03
+0214  F0000210          MOV I, LONG 0x0210
+0218  1212              JUMP L212
+";
+    assert_eq!(listing.to_string(), expected);
+}
+
+#[test]
+fn labels_name_jump_and_call_targets() {
+    let listing = disassemble(ROM, Platform::XoChip);
+
+    let labels: Vec<(u16, &str)> = listing.labels.iter().map(|(address, label)| (*address, label.as_str())).collect();
+    assert_eq!(labels, vec![(0x200, "L200"), (0x20A, "S20A"), (0x212, "L212")]);
+    assert_eq!(listing.label(0x208), None);
+}
+
+/// A skip over `F000 NNNN` lands after all four bytes, but only on XO-CHIP;
+/// elsewhere `F000` isn't an instruction and the rest is data.
+#[test]
+fn skips_follow_the_platform() {
+    let listing = disassemble(ROM, Platform::Chip8);
+
+    let tail: Vec<(u16, bool)> = listing
+        .lines
+        .iter()
+        .skip_while(|line| line.address < 0x212)
+        .map(|line| (line.address, line.instruction.is_some()))
+        .collect();
+    assert_eq!(tail, vec![(0x212, true), (0x214, false)]);
+    assert_eq!(listing.lines.last().unwrap().bytes, vec![0xF0, 0x00, 0x02, 0x10, 0x12, 0x12]);
+}
+
+#[test]
+fn long_data_runs_are_split() {
+    let rom = [0x12, 0x0C, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 0x12, 0x0C];
+    let listing = disassemble(&rom, Platform::Chip8);
+
+    let sizes: Vec<usize> = listing.lines.iter().map(|line| line.bytes.len()).collect();
+    assert_eq!(sizes, vec![2, 8, 2, 2]);
+}
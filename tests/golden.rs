@@ -0,0 +1,150 @@
+//! Golden-frame regression tests.
+//!
+//! Each case runs a ROM headlessly with a fixed seed and scripted keypad
+//! input and compares the screen at chosen frames with the images stored in
+//! `tests/golden`, one text file per frame (`.` unlit, `#` lit). Besides the
+//! bundled ROMs, every `.ch8` file dropped into `tests/roms` is checked at
+//! its default frames with no input.
+//!
+//! Run with `BLESS=1` to write the current frames as the new goldens. On a
+//! mismatch the test prints a diff (`+` lit but expected unlit, `-` unlit but
+//! expected lit) and leaves the actual frame next to it in the cargo target
+//! directory.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chip8::clock::{instructions_per_frame, DEFAULT_IPS};
+use chip8::movie::set_keypad;
+use chip8::runner::screen_rows;
+use chip8::Chip8;
+
+/// Frames checked for ROMs that don't have a case of their own.
+const DEFAULT_FRAMES: &[usize] = &[60, 300];
+
+struct Case {
+    rom: &'static str,
+    seed: u64,
+    /// `(first frame, keypad bitmask)`: keys held from that frame until the
+    /// next entry.
+    input: &'static [(usize, u16)],
+    frames: &'static [usize],
+}
+
+fn check(rom: &Path, case: &Case) {
+    let name = rom.file_stem().unwrap().to_string_lossy().to_string();
+    let bless = env::var_os("BLESS").is_some();
+
+    let mut chip8 = Chip8::new();
+    chip8.load(rom.to_str().unwrap()).unwrap();
+    chip8.seed(case.seed);
+    chip8.trace = None;
+
+    let mut failures = Vec::new();
+    let last = case.frames.iter().copied().max().unwrap_or(0);
+    for frame in 1..=last {
+        if let Some((_, keys)) = case.input.iter().rev().find(|(start, _)| *start < frame) {
+            set_keypad(&mut chip8.keypad, *keys);
+        }
+        chip8.run_frame(instructions_per_frame(DEFAULT_IPS)).unwrap();
+        if !case.frames.contains(&frame) {
+            continue;
+        }
+
+        let actual = screen_rows(&chip8).join("\n") + "\n";
+        let golden = golden_dir().join(format!("{}-{}.txt", name, frame));
+        if bless {
+            fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&golden)
+            .unwrap_or_else(|err| panic!("cannot read {}: {} (run with BLESS=1 to create it)", golden.display(), err));
+        if actual != expected {
+            let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}-{}.actual.txt", name, frame));
+            fs::write(&out, &actual).unwrap();
+            failures.push(format!("{} frame {} (actual frame in {}):\n{}", name, frame, out.display(), diff(&expected, &actual)));
+        }
+    }
+
+    assert!(failures.is_empty(), "screens differ from the goldens:\n\n{}", failures.join("\n"));
+}
+
+/// Overlay two frames: unchanged pixels as they are, `+` for pixels only lit
+/// in `actual` and `-` for pixels only lit in `expected`.
+fn diff(expected: &str, actual: &str) -> String {
+    let mut out = String::new();
+    let mut expected_rows = expected.lines();
+    for actual_row in actual.lines() {
+        let expected_row = expected_rows.next().unwrap_or("");
+        let mut expected_pixels = expected_row.chars();
+        for pixel in actual_row.chars() {
+            out.push(match (expected_pixels.next().unwrap_or('.'), pixel) {
+                (old, new) if old == new => new,
+                ('.', _) => '+',
+                (_, '.') => '-',
+                _ => '*',
+            });
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn bundled(case: Case) {
+    check(&Path::new(env!("CARGO_MANIFEST_DIR")).join(case.rom), &case);
+}
+
+#[test]
+fn pong() {
+    bundled(Case {
+        rom: "pong.ch8",
+        seed: 1,
+        // Left paddle up, then down.
+        input: &[(60, 1 << 0x1), (120, 1 << 0x4), (180, 0)],
+        frames: &[30, 120, 240],
+    });
+}
+
+#[test]
+fn space() {
+    bundled(Case {
+        rom: "space.ch8",
+        seed: 1,
+        // Move left, fire, move right.
+        input: &[(60, 1 << 0x4), (90, 1 << 0x5), (100, 0), (110, 1 << 0x6), (140, 0)],
+        frames: &[60, 200, 400],
+    });
+}
+
+#[test]
+fn particles() {
+    bundled(Case { rom: "particles.ch8", seed: 1, input: &[], frames: &[30, 120] });
+}
+
+#[test]
+fn zero() {
+    bundled(Case { rom: "zero.ch8", seed: 1, input: &[], frames: &[30, 120] });
+}
+
+#[test]
+fn community_roms() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("roms");
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    let mut roms: Vec<PathBuf> = entries.filter_map(Result::ok).map(|entry| entry.path()).collect();
+    roms.retain(|path| path.extension().is_some_and(|extension| extension == "ch8"));
+    roms.sort();
+    for rom in roms {
+        check(&rom, &Case { rom: "", seed: 1, input: &[], frames: DEFAULT_FRAMES });
+    }
+}
@@ -0,0 +1,83 @@
+//! Movie files: formatting and parsing round trip, and what is rejected.
+
+mod common;
+
+use chip8::movie::{Movie, MAX_FRAMES};
+use chip8::{Platform, Quirks};
+
+use common::error;
+
+/// Why `text` isn't a movie.
+fn rejected(text: &str) -> String {
+    error(text.parse::<Movie>())
+}
+
+const FILE: &str = "\
+chip8-movie 1
+rom 6E5A7A9F
+platform schip
+quirks shift_vy=off,memory_increment=off,jump_vx=on,vf_reset=off,wrap_sprites=off,display_wait=off
+seed 42
+ips 1000
+frames
+0000*3
+0010
+8001*2
+0000
+";
+
+fn movie() -> Movie {
+    Movie {
+        rom: 0x6E5A_7A9F,
+        platform: Platform::SuperChip,
+        quirks: Quirks::schip(),
+        seed: 42,
+        ips: 1000,
+        frames: vec![0, 0, 0, 0x10, 0x8001, 0x8001, 0],
+    }
+}
+
+#[test]
+fn formats_runs_of_frames() {
+    assert_eq!(movie().to_string(), FILE);
+}
+
+#[test]
+fn parse_and_format_round_trip() {
+    assert_eq!(FILE.parse::<Movie>().unwrap(), movie());
+
+    let mut long = movie();
+    long.quirks = Quirks::xochip();
+    long.platform = Platform::XoChip;
+    long.seed = u64::MAX;
+    long.frames = (0..500u16).map(|i| if i % 7 < 3 { i & 0xF } else { 0xFFFF }).collect();
+    assert_eq!(long.to_string().parse::<Movie>().unwrap(), long);
+}
+
+#[test]
+fn empty_movies_round_trip() {
+    let mut empty = movie();
+    empty.frames.clear();
+    assert!(empty.to_string().ends_with("frames\n"));
+    assert_eq!(empty.to_string().parse::<Movie>().unwrap(), empty);
+}
+
+#[test]
+fn rejects_bad_files() {
+    assert_eq!(rejected("chip8-movie 2\n"), "bad movie: not a movie file");
+    assert_eq!(rejected(&FILE.replace("seed 42", "seed x")), "bad movie: line 5: bad seed `x`");
+    assert_eq!(rejected(&FILE.replace("8001*2", "8001*")), "bad movie: line 10: bad frame `8001*`");
+    assert_eq!(rejected(&FILE.replace("ips 1000", "speed 1000")), "bad movie: line 6: unknown key `speed`");
+    assert_eq!(rejected(&FILE.replace("ips 1000\n", "")), "bad movie: missing `ips`");
+}
+
+#[test]
+fn rejects_too_many_frames() {
+    assert_eq!(rejected(&FILE.replace("8001*2", "8001*99999999999")), "bad movie: line 10: more than 5184000 frames");
+
+    // The limit is on the whole movie, not each line.
+    let half = format!("0000*{}\n", MAX_FRAMES / 2);
+    let full = format!("{}frames\n{}{}", &FILE[..FILE.find("frames").unwrap()], half, half);
+    assert_eq!(full.parse::<Movie>().unwrap().frames.len(), MAX_FRAMES);
+    assert_eq!(rejected(&format!("{}0000\n", full)), "bad movie: line 10: more than 5184000 frames");
+}
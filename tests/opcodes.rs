@@ -0,0 +1,663 @@
+//! One or more tests for every instruction `execute_opcode` knows, checked
+//! against the CHIP-8, SUPER-CHIP and XO-CHIP specifications, including VF
+//! and each quirk that changes the result.
+
+mod common;
+
+use chip8::{Chip8, Chip8Error, KeyWait, Platform, Quirks, HIRES_HEIGHT, HIRES_WIDTH};
+
+/// A CHIP-8 machine with the modern quirks and `program` loaded at 0x200.
+fn machine(program: &[u16]) -> Chip8 {
+    machine_on(Platform::Chip8, Quirks::modern(), program)
+}
+
+fn machine_on(platform: Platform, quirks: Quirks, program: &[u16]) -> Chip8 {
+    let mut chip8 = common::load(Chip8::with_platform(platform, quirks), program);
+    chip8.seed(1);
+    chip8.trace = None;
+    chip8
+}
+
+fn run(chip8: &mut Chip8, instructions: usize) {
+    for _ in 0..instructions {
+        chip8.cycle().unwrap();
+    }
+}
+
+/// Run a single `8XYN` with Vx = `vx` and Vy = `vy` in V1 and V2, returning
+/// (V1, VF).
+fn alu(opcode: u16, vx: u8, vy: u8, quirks: Quirks) -> (u8, u8) {
+    let mut chip8 = machine_on(Platform::Chip8, quirks, &[opcode]);
+    chip8.registers[1] = vx;
+    chip8.registers[2] = vy;
+    chip8.registers[0xF] = 0xAA;
+    run(&mut chip8, 1);
+    (chip8.registers[1], chip8.registers[0xF])
+}
+
+fn lit(chip8: &Chip8) -> Vec<(usize, usize)> {
+    let width = chip8.width;
+    chip8.screen.iter().enumerate().filter(|(_, pixel)| **pixel != 0).map(|(i, _)| (i % width, i / width)).collect()
+}
+
+#[test]
+fn clear_screen() {
+    let mut chip8 = machine(&[0x00E0]);
+    chip8.screen.iter_mut().for_each(|pixel| *pixel = 1);
+    run(&mut chip8, 1);
+    assert!(lit(&chip8).is_empty());
+}
+
+#[test]
+fn call_and_return() {
+    let mut chip8 = machine(&[0x2206, 0x0000, 0x0000, 0x00EE]);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.pc, 0x206);
+    assert_eq!(chip8.stack, vec![0x202]);
+
+    run(&mut chip8, 1);
+    assert_eq!(chip8.pc, 0x202);
+    assert!(chip8.stack.is_empty());
+}
+
+#[test]
+fn return_with_empty_stack_faults() {
+    let mut chip8 = machine(&[0x00EE]);
+    match chip8.cycle() {
+        Err(Chip8Error::StackUnderflow { pc: 0x200, opcode: 0x00EE }) => {}
+        other => panic!("expected a stack underflow, got {:?}", other),
+    }
+}
+
+#[test]
+fn call_overflows_after_sixteen_levels() {
+    // Calls itself forever.
+    let mut chip8 = machine(&[0x2200]);
+    run(&mut chip8, 16);
+    match chip8.cycle() {
+        Err(Chip8Error::StackOverflow { pc: 0x200, opcode: 0x2200 }) => {}
+        other => panic!("expected a stack overflow, got {:?}", other),
+    }
+}
+
+#[test]
+fn jump() {
+    let mut chip8 = machine(&[0x1ABC]);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.pc, 0xABC);
+}
+
+#[test]
+fn jump_plus_v0() {
+    let mut chip8 = machine(&[0xB300]);
+    chip8.registers[0] = 0x10;
+    chip8.registers[3] = 0x20;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.pc, 0x310);
+}
+
+#[test]
+fn jump_plus_vx_quirk() {
+    let quirks = Quirks { jump_vx: true, ..Quirks::modern() };
+    let mut chip8 = machine_on(Platform::Chip8, quirks, &[0xB300]);
+    chip8.registers[0] = 0x10;
+    chip8.registers[3] = 0x20;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.pc, 0x320);
+}
+
+#[test]
+fn skips() {
+    // (opcode, V1, V2, skipped)
+    let cases = [
+        (0x3142, 0x42, 0, true),
+        (0x3142, 0x41, 0, false),
+        (0x4142, 0x42, 0, false),
+        (0x4142, 0x41, 0, true),
+        (0x5120, 7, 7, true),
+        (0x5120, 7, 8, false),
+        (0x9120, 7, 7, false),
+        (0x9120, 7, 8, true),
+    ];
+
+    for (opcode, v1, v2, skipped) in cases.iter() {
+        let mut chip8 = machine(&[*opcode]);
+        chip8.registers[1] = *v1;
+        chip8.registers[2] = *v2;
+        run(&mut chip8, 1);
+        assert_eq!(chip8.pc, if *skipped { 0x204 } else { 0x202 }, "{:04X} with V1={} V2={}", opcode, v1, v2);
+    }
+}
+
+#[test]
+fn skips_over_long_load_on_xochip() {
+    let mut chip8 = machine_on(Platform::XoChip, Quirks::xochip(), &[0x3100, 0xF000, 0x1234]);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.pc, 0x206);
+}
+
+#[test]
+fn load_and_add_immediate() {
+    let mut chip8 = machine(&[0x6AF0, 0x7A20]);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.registers[0xA], 0xF0);
+
+    // 7XNN wraps and leaves VF alone.
+    chip8.registers[0xF] = 0x55;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.registers[0xA], 0x10);
+    assert_eq!(chip8.registers[0xF], 0x55);
+}
+
+#[test]
+fn logic() {
+    let modern = Quirks::modern();
+    assert_eq!(alu(0x8120, 0x0F, 0x3C, modern), (0x3C, 0xAA));
+    assert_eq!(alu(0x8121, 0x0F, 0x3C, modern), (0x3F, 0xAA));
+    assert_eq!(alu(0x8122, 0x0F, 0x3C, modern), (0x0C, 0xAA));
+    assert_eq!(alu(0x8123, 0x0F, 0x3C, modern), (0x33, 0xAA));
+}
+
+#[test]
+fn logic_vf_reset_quirk() {
+    let quirks = Quirks { vf_reset: true, ..Quirks::modern() };
+    assert_eq!(alu(0x8121, 0x0F, 0x3C, quirks), (0x3F, 0));
+    assert_eq!(alu(0x8122, 0x0F, 0x3C, quirks), (0x0C, 0));
+    assert_eq!(alu(0x8123, 0x0F, 0x3C, quirks), (0x33, 0));
+    // 8XY0 is not a logic operation.
+    assert_eq!(alu(0x8120, 0x0F, 0x3C, quirks), (0x3C, 0xAA));
+}
+
+#[test]
+fn add_with_carry() {
+    let modern = Quirks::modern();
+    assert_eq!(alu(0x8124, 0x10, 0x20, modern), (0x30, 0));
+    assert_eq!(alu(0x8124, 0xFF, 0x01, modern), (0x00, 1));
+    assert_eq!(alu(0x8124, 0xF0, 0x20, modern), (0x10, 1));
+}
+
+#[test]
+fn subtract() {
+    let modern = Quirks::modern();
+    assert_eq!(alu(0x8125, 0x30, 0x10, modern), (0x20, 1));
+    // Equal operands don't borrow.
+    assert_eq!(alu(0x8125, 0x30, 0x30, modern), (0x00, 1));
+    assert_eq!(alu(0x8125, 0x10, 0x30, modern), (0xE0, 0));
+}
+
+#[test]
+fn subtract_reversed() {
+    let modern = Quirks::modern();
+    assert_eq!(alu(0x8127, 0x10, 0x30, modern), (0x20, 1));
+    assert_eq!(alu(0x8127, 0x30, 0x30, modern), (0x00, 1));
+    assert_eq!(alu(0x8127, 0x30, 0x10, modern), (0xE0, 0));
+}
+
+#[test]
+fn shifts() {
+    let modern = Quirks::modern();
+    assert_eq!(alu(0x8126, 0b1000_0101, 0xFF, modern), (0b0100_0010, 1));
+    assert_eq!(alu(0x8126, 0b1000_0100, 0xFF, modern), (0b0100_0010, 0));
+    assert_eq!(alu(0x812E, 0b1000_0101, 0xFF, modern), (0b0000_1010, 1));
+    assert_eq!(alu(0x812E, 0b0100_0101, 0xFF, modern), (0b1000_1010, 0));
+}
+
+#[test]
+fn shifts_vy_quirk() {
+    let quirks = Quirks { shift_vy: true, ..Quirks::modern() };
+    assert_eq!(alu(0x8126, 0xFF, 0b0000_0110, quirks), (0b0000_0011, 0));
+    assert_eq!(alu(0x812E, 0x00, 0b1100_0000, quirks), (0b1000_0000, 1));
+}
+
+/// With VF as the destination the flag overwrites the result.
+#[test]
+fn flag_wins_over_vf_result() {
+    // (opcode, VF, V2, VF afterwards)
+    let cases = [
+        (0x8F24, 0xFF, 0x01, 1),
+        (0x8F24, 0x01, 0x01, 0),
+        (0x8F25, 0x30, 0x10, 1),
+        (0x8F25, 0x10, 0x30, 0),
+        (0x8F27, 0x10, 0x30, 1),
+        (0x8F27, 0x30, 0x10, 0),
+        (0x8F26, 0b10, 0, 0),
+        (0x8F26, 0b11, 0, 1),
+        (0x8F2E, 0x40, 0, 0),
+        (0x8F2E, 0x80, 0, 1),
+    ];
+
+    for (opcode, vf, v2, expected) in cases.iter() {
+        let mut chip8 = machine(&[*opcode]);
+        chip8.registers[0xF] = *vf;
+        chip8.registers[2] = *v2;
+        run(&mut chip8, 1);
+        assert_eq!(chip8.registers[0xF], *expected, "{:04X} with VF={:02X} V2={:02X}", opcode, vf, v2);
+    }
+}
+
+#[test]
+fn load_index() {
+    let mut chip8 = machine(&[0xA123]);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.index, 0x123);
+}
+
+#[test]
+fn random_is_masked_and_seeded() {
+    let mut first = machine(&[0xC10F, 0xC2F0]);
+    run(&mut first, 2);
+    assert_eq!(first.registers[1] & 0xF0, 0);
+    assert_eq!(first.registers[2] & 0x0F, 0);
+
+    let mut second = machine(&[0xC10F, 0xC2F0]);
+    run(&mut second, 2);
+    assert_eq!(first.registers, second.registers);
+}
+
+#[test]
+fn draw_sets_and_erases() {
+    let mut chip8 = machine(&[0xD125, 0xD125]);
+    chip8.registers[1] = 10;
+    chip8.registers[2] = 4;
+    // The font's "1": ..#. / .##. / ..#. / ..#. / .###
+    chip8.index = 0x55;
+    run(&mut chip8, 1);
+    assert_eq!(lit(&chip8), vec![(12, 4), (11, 5), (12, 5), (12, 6), (12, 7), (11, 8), (12, 8), (13, 8)]);
+    assert_eq!(chip8.registers[0xF], 0);
+
+    run(&mut chip8, 1);
+    assert!(lit(&chip8).is_empty());
+    assert_eq!(chip8.registers[0xF], 1);
+}
+
+#[test]
+fn draw_collision_is_per_sprite() {
+    let mut chip8 = machine(&[0xD021, 0xD121]);
+    chip8.memory[0x300] = 0b1000_0000;
+    chip8.index = 0x300;
+    chip8.registers[1] = 1;
+    run(&mut chip8, 2);
+    // The second sprite lands next to the first, not on it.
+    assert_eq!(chip8.registers[0xF], 0);
+    assert_eq!(lit(&chip8), vec![(0, 0), (1, 0)]);
+}
+
+#[test]
+fn draw_display_wait_quirk() {
+    let quirks = Quirks { display_wait: true, ..Quirks::modern() };
+    let mut chip8 = machine_on(Platform::Chip8, quirks, &[0xD011, 0xD011, 0x6001]);
+    chip8.run_frame(10).unwrap();
+    assert_eq!(chip8.pc, 0x202);
+    chip8.run_frame(10).unwrap();
+    assert_eq!(chip8.pc, 0x204);
+}
+
+/// A machine about to run `D01N` at (V0, V1) with `rows` of the sprite at 0x300.
+fn sprite(platform: Platform, quirks: Quirks, x: u8, y: u8, rows: &[u8]) -> Chip8 {
+    let mut chip8 = machine_on(platform, quirks, &[0xD010 | rows.len() as u16, 0xD010 | rows.len() as u16]);
+    chip8.registers[0] = x;
+    chip8.registers[1] = y;
+    chip8.index = 0x300;
+    chip8.memory[0x300..0x300 + rows.len()].copy_from_slice(rows);
+    chip8
+}
+
+fn wrapping() -> Quirks {
+    Quirks { wrap_sprites: true, ..Quirks::modern() }
+}
+
+#[test]
+fn draw_start_wraps() {
+    for quirks in [Quirks::modern(), wrapping()].iter() {
+        let mut chip8 = sprite(Platform::Chip8, *quirks, 64 + 10, 32 * 3 + 5, &[0b1000_0000]);
+        run(&mut chip8, 1);
+        assert_eq!(lit(&chip8), vec![(10, 5)], "wrap_sprites: {}", quirks.wrap_sprites);
+    }
+}
+
+#[test]
+fn draw_clips_at_the_edges() {
+    let mut chip8 = sprite(Platform::Chip8, Quirks::modern(), 61, 30, &[0b1111_0001, 0b1000_0000, 0b1111_1111]);
+    run(&mut chip8, 1);
+    assert_eq!(lit(&chip8), vec![(61, 30), (62, 30), (63, 30), (61, 31)]);
+    assert_eq!(chip8.registers[0xF], 0);
+}
+
+#[test]
+fn draw_wraps_at_the_edges_under_quirk() {
+    let mut chip8 = sprite(Platform::Chip8, wrapping(), 62, 31, &[0b1010_0000, 0b0100_0000]);
+    run(&mut chip8, 1);
+    assert_eq!(lit(&chip8), vec![(63, 0), (0, 31), (62, 31)]);
+}
+
+#[test]
+fn draw_collides_across_the_edge_only_when_wrapping() {
+    // The second row would land on the first if it wrapped.
+    let mut chip8 = sprite(Platform::Chip8, Quirks::modern(), 0, 31, &[0b1000_0000, 0b1000_0000]);
+    chip8.screen[0] = 1;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.registers[0xF], 0);
+    assert!(chip8.screen[0] == 1);
+
+    let mut chip8 = sprite(Platform::Chip8, wrapping(), 0, 31, &[0b1000_0000, 0b1000_0000]);
+    chip8.screen[0] = 1;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.registers[0xF], 1);
+    assert!(chip8.screen[0] == 0);
+}
+
+#[test]
+fn draw_in_hires_wraps_at_the_hires_edges() {
+    let mut chip8 = machine_on(Platform::SuperChip, Quirks::schip(), &[0x00FF, 0xD011]);
+    chip8.registers[0] = 128 + 127;
+    chip8.registers[1] = 64 + 63;
+    chip8.index = 0x300;
+    chip8.memory[0x300] = 0b1100_0000;
+    run(&mut chip8, 2);
+    assert_eq!(lit(&chip8), vec![(127, 63)]);
+}
+
+#[test]
+fn schip_hires_counts_collided_rows() {
+    let mut chip8 = sprite(Platform::SuperChip, Quirks::schip(), 4, 4, &[0xFF, 0x00, 0x81, 0x18]);
+    chip8.set_hires(true);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.registers[0xF], 0);
+    // Three rows have pixels, and they all collide with themselves.
+    run(&mut chip8, 1);
+    assert_eq!(chip8.registers[0xF], 3);
+}
+
+#[test]
+fn schip_hires_counts_rows_clipped_off_the_bottom() {
+    let mut chip8 = sprite(Platform::SuperChip, Quirks::schip(), 0, 60, &[0x80; 6]);
+    chip8.set_hires(true);
+    chip8.screen[60 * HIRES_WIDTH] = 1;
+    run(&mut chip8, 1);
+    // One collision at the top plus two rows past row 63.
+    assert_eq!(chip8.registers[0xF], 3);
+}
+
+#[test]
+fn schip_lores_and_xochip_flag_collisions_once() {
+    let lores = sprite(Platform::SuperChip, Quirks::schip(), 0, 0, &[0xFF, 0xFF, 0xFF]);
+    let mut hires_xochip = sprite(Platform::XoChip, Quirks::xochip(), 0, 0, &[0xFF, 0xFF, 0xFF]);
+    hires_xochip.set_hires(true);
+
+    for mut chip8 in [lores, hires_xochip] {
+        run(&mut chip8, 2);
+        assert_eq!(chip8.registers[0xF], 1, "{}", chip8.platform);
+    }
+}
+
+#[test]
+fn draw_past_memory_faults() {
+    let mut chip8 = machine(&[0xD01F]);
+    chip8.index = 0xFF8;
+    match chip8.cycle() {
+        Err(Chip8Error::MemoryOutOfBounds { pc: 0x200, opcode: 0xD01F, .. }) => {}
+        other => panic!("expected an out of bounds read, got {:?}", other),
+    }
+}
+
+#[test]
+fn draw_big_sprite_on_schip() {
+    let mut chip8 = machine_on(Platform::SuperChip, Quirks::schip(), &[0x00FF, 0xD010]);
+    chip8.index = 0x300;
+    for byte in chip8.memory[0x300..0x320].iter_mut() {
+        *byte = 0xFF;
+    }
+    run(&mut chip8, 2);
+    assert_eq!(lit(&chip8).len(), 16 * 16);
+}
+
+#[test]
+fn draw_on_both_xochip_planes() {
+    let mut chip8 = machine_on(Platform::XoChip, Quirks::xochip(), &[0xF301, 0xD011]);
+    chip8.index = 0x300;
+    chip8.memory[0x300] = 0b1000_0000;
+    chip8.memory[0x301] = 0b1100_0000;
+    run(&mut chip8, 2);
+    assert_eq!(chip8.screen[0], 0b11);
+    assert_eq!(chip8.screen[1], 0b10);
+}
+
+#[test]
+fn key_skips() {
+    // (opcode, key down, skipped)
+    let cases = [(0xE19E, true, true), (0xE19E, false, false), (0xE1A1, true, false), (0xE1A1, false, true)];
+    for (opcode, down, skipped) in cases.iter() {
+        let mut chip8 = machine(&[*opcode]);
+        chip8.registers[1] = 0xB;
+        chip8.keypad[0xB] = *down;
+        run(&mut chip8, 1);
+        assert_eq!(chip8.pc, if *skipped { 0x204 } else { 0x202 }, "{:04X} with the key down: {}", opcode, down);
+    }
+}
+
+#[test]
+fn wait_for_key_press_and_release() {
+    let mut chip8 = machine(&[0xF30A, 0x6001]);
+    run(&mut chip8, 3);
+    assert_eq!(chip8.key_wait, Some(KeyWait { register: 3, pressed: None }));
+    assert_eq!(chip8.pc, 0x202);
+
+    chip8.keypad[7] = true;
+    run(&mut chip8, 2);
+    assert_eq!(chip8.key_wait, Some(KeyWait { register: 3, pressed: Some(7) }));
+
+    chip8.keypad[7] = false;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.key_wait, None);
+    assert_eq!(chip8.registers[3], 7);
+    assert_eq!(chip8.pc, 0x202);
+}
+
+#[test]
+fn timers() {
+    let mut chip8 = machine(&[0xF115, 0xF218, 0xF307]);
+    chip8.registers[1] = 5;
+    chip8.registers[2] = 9;
+    run(&mut chip8, 2);
+    assert_eq!((chip8.timer, chip8.sound_timer), (5, 9));
+
+    chip8.tick_timers();
+    run(&mut chip8, 1);
+    assert_eq!(chip8.registers[3], 4);
+    assert_eq!(chip8.sound_timer, 8);
+}
+
+#[test]
+fn add_to_index() {
+    let mut chip8 = machine(&[0xF11E]);
+    chip8.index = 0xFFF;
+    chip8.registers[1] = 2;
+    chip8.registers[0xF] = 0x55;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.index, 0x1001);
+    assert_eq!(chip8.registers[0xF], 0x55);
+}
+
+#[test]
+fn font_characters() {
+    let mut chip8 = machine(&[0xF129]);
+    chip8.registers[1] = 0xA;
+    run(&mut chip8, 1);
+    assert_eq!(&chip8.memory[chip8.index as usize..chip8.index as usize + 5], &[0xF0, 0x90, 0xF0, 0x90, 0x90]);
+
+    let mut chip8 = machine_on(Platform::SuperChip, Quirks::schip(), &[0xF130]);
+    chip8.registers[1] = 0x1;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.memory[chip8.index as usize..chip8.index as usize + 10][9], 0x3C);
+}
+
+#[test]
+fn binary_coded_decimal() {
+    let mut chip8 = machine(&[0xF133]);
+    chip8.registers[1] = 254;
+    chip8.index = 0x300;
+    run(&mut chip8, 1);
+    assert_eq!(&chip8.memory[0x300..0x303], &[2, 5, 4]);
+    assert_eq!(chip8.index, 0x300);
+}
+
+#[test]
+fn store_and_load_registers() {
+    let mut chip8 = machine(&[0xF255, 0xA304, 0xF165]);
+    chip8.registers[..4].copy_from_slice(&[1, 2, 3, 4]);
+    chip8.index = 0x300;
+    chip8.memory[0x304] = 0xAA;
+    chip8.memory[0x305] = 0xBB;
+    run(&mut chip8, 1);
+    assert_eq!(&chip8.memory[0x300..0x304], &[1, 2, 3, 0]);
+    assert_eq!(chip8.index, 0x300);
+
+    run(&mut chip8, 2);
+    assert_eq!(&chip8.registers[..4], &[0xAA, 0xBB, 3, 4]);
+    assert_eq!(chip8.index, 0x304);
+}
+
+#[test]
+fn store_and_load_registers_memory_increment_quirk() {
+    let quirks = Quirks { memory_increment: true, ..Quirks::modern() };
+    let mut chip8 = machine_on(Platform::Chip8, quirks, &[0xF255, 0xF165]);
+    chip8.index = 0x300;
+    run(&mut chip8, 1);
+    assert_eq!(chip8.index, 0x303);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.index, 0x305);
+}
+
+#[test]
+fn store_registers_past_memory_faults() {
+    let mut chip8 = machine(&[0xFF55]);
+    chip8.index = 0xFF8;
+    match chip8.cycle() {
+        Err(Chip8Error::MemoryOutOfBounds { pc: 0x200, opcode: 0xFF55, address: 0x1000 }) => {}
+        other => panic!("expected an out of bounds write, got {:?}", other),
+    }
+}
+
+#[test]
+fn extensions_are_unknown_on_chip8() {
+    for opcode in [0x00FF, 0x00FE, 0x00FB, 0x00FC, 0x00FD, 0x00C1, 0xF130, 0xF175, 0xF185, 0x5122, 0xF000].iter() {
+        let mut chip8 = machine(&[*opcode]);
+        match chip8.cycle() {
+            Err(Chip8Error::UnknownOpcode { pc: 0x200, opcode: raw }) if raw == *opcode => {}
+            other => panic!("expected {:04X} to be unknown, got {:?}", opcode, other),
+        }
+    }
+}
+
+#[test]
+fn schip_resolution() {
+    let mut chip8 = machine_on(Platform::SuperChip, Quirks::schip(), &[0x00FF, 0x00FE]);
+    chip8.screen[0] = 1;
+    run(&mut chip8, 1);
+    assert!(chip8.hires);
+    assert_eq!((chip8.width, chip8.height), (HIRES_WIDTH, HIRES_HEIGHT));
+    assert!(lit(&chip8).is_empty());
+
+    run(&mut chip8, 1);
+    assert!(!chip8.hires);
+    assert_eq!(chip8.screen.len(), 64 * 32);
+}
+
+#[test]
+fn schip_scrolling() {
+    let mut chip8 = machine_on(Platform::SuperChip, Quirks::schip(), &[0x00C3, 0x00FB, 0x00FC]);
+    chip8.screen[0] = 1;
+    run(&mut chip8, 1);
+    assert_eq!(lit(&chip8), vec![(0, 3)]);
+    run(&mut chip8, 1);
+    assert_eq!(lit(&chip8), vec![(4, 3)]);
+    run(&mut chip8, 1);
+    assert_eq!(lit(&chip8), vec![(0, 3)]);
+}
+
+#[test]
+fn xochip_scroll_up() {
+    let mut chip8 = machine_on(Platform::XoChip, Quirks::xochip(), &[0x00D2]);
+    chip8.screen[64 * 5] = 1;
+    run(&mut chip8, 1);
+    assert_eq!(lit(&chip8), vec![(0, 3)]);
+}
+
+#[test]
+fn schip_exit() {
+    let mut chip8 = machine_on(Platform::SuperChip, Quirks::schip(), &[0x00FD, 0x6001]);
+    run(&mut chip8, 3);
+    assert!(chip8.halted);
+    assert_eq!(chip8.pc, 0x202);
+    assert_eq!(chip8.registers[0], 0);
+}
+
+#[test]
+fn schip_user_flags() {
+    let mut chip8 = machine_on(Platform::SuperChip, Quirks::schip(), &[0xF275, 0x6000, 0xF285]);
+    chip8.registers[..3].copy_from_slice(&[7, 8, 9]);
+    run(&mut chip8, 2);
+    assert_eq!(&chip8.flags[..3], &[7, 8, 9]);
+    assert_eq!(chip8.registers[0], 0);
+    run(&mut chip8, 1);
+    assert_eq!(&chip8.registers[..3], &[7, 8, 9]);
+
+    // SUPER-CHIP only has eight flags.
+    for opcode in [0xF875, 0xF885] {
+        let mut chip8 = machine_on(Platform::SuperChip, Quirks::schip(), &[opcode]);
+        match chip8.cycle() {
+            Err(Chip8Error::FlagsOutOfRange { pc: 0x200, opcode: raw, register: 8, flags: 8 }) if raw == opcode => {}
+            other => panic!("{:04X}: expected the flags to be out of range, got {:?}", opcode, other),
+        }
+    }
+}
+
+#[test]
+fn xochip_register_ranges() {
+    let mut chip8 = machine_on(Platform::XoChip, Quirks::xochip(), &[0x5132, 0x5312, 0x5543]);
+    chip8.registers[1..4].copy_from_slice(&[1, 2, 3]);
+    chip8.index = 0x300;
+    run(&mut chip8, 1);
+    assert_eq!(&chip8.memory[0x300..0x303], &[1, 2, 3]);
+    // The range doesn't move I.
+    assert_eq!(chip8.index, 0x300);
+
+    chip8.index = 0x310;
+    run(&mut chip8, 1);
+    assert_eq!(&chip8.memory[0x310..0x313], &[3, 2, 1]);
+
+    // V5 down to V4 from [3, 2].
+    chip8.index = 0x310;
+    run(&mut chip8, 1);
+    assert_eq!(&chip8.registers[4..6], &[2, 3]);
+}
+
+#[test]
+fn xochip_long_index() {
+    let mut chip8 = machine_on(Platform::XoChip, Quirks::xochip(), &[0xF000, 0xBEEF]);
+    run(&mut chip8, 1);
+    assert_eq!(chip8.index, 0xBEEF);
+    assert_eq!(chip8.pc, 0x204);
+}
+
+#[test]
+fn xochip_planes_and_audio() {
+    let mut chip8 = machine_on(Platform::XoChip, Quirks::xochip(), &[0xF201, 0xF002, 0xF13A]);
+    chip8.index = 0x300;
+    for (i, byte) in chip8.memory[0x300..0x310].iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    chip8.registers[1] = 100;
+    run(&mut chip8, 3);
+    assert_eq!(chip8.planes, 2);
+    assert_eq!(chip8.audio_pattern, Some([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]));
+    assert_eq!(chip8.pitch, 100);
+}
+
+#[test]
+fn xochip_clear_only_touches_selected_planes() {
+    let mut chip8 = machine_on(Platform::XoChip, Quirks::xochip(), &[0xF101, 0x00E0]);
+    chip8.screen[0] = 0b11;
+    run(&mut chip8, 2);
+    assert_eq!(chip8.screen[0], 0b10);
+}
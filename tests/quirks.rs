@@ -0,0 +1,28 @@
+//! Quirks presets: each is its own profile, and the differences show.
+
+mod common;
+
+use chip8::quirks::PRESETS;
+use chip8::{Chip8, Platform, Quirks};
+
+#[test]
+fn presets_are_distinct() {
+    for (i, first) in PRESETS.iter().enumerate() {
+        for second in &PRESETS[i + 1..] {
+            assert_ne!(Quirks::preset(first), Quirks::preset(second), "{} and {}", first, second);
+        }
+    }
+}
+
+#[test]
+fn chip48_moves_i_and_schip_does_not() {
+    let index_after = |quirks: Quirks| {
+        let mut chip8 = common::load(Chip8::with_platform(Platform::SuperChip, quirks), &[0xF255]);
+        chip8.index = 0x300;
+        chip8.cycle().unwrap();
+        chip8.index
+    };
+
+    assert_eq!(index_after(Quirks::chip48()), 0x303);
+    assert_eq!(index_after(Quirks::schip()), 0x300);
+}
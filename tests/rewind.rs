@@ -0,0 +1,73 @@
+//! Rewinding: stepping back lands on exactly the state the machine was in.
+
+mod common;
+
+use chip8::rewind::Rewind;
+use chip8::{Chip8, APP_START};
+
+/// Counts in V0, draws random sprites and reacts to key 5.
+const PROGRAM: &[u16] = &[
+    0x7001, // V0 += 1
+    0xC1FF, // V1 = random
+    0xF029, // I = glyph for V0
+    0xD105, // draw at (V1, V0)
+    0x6205, // V2 = 5
+    0xE29E, // skip if key V2 is down
+    0x1200, // jump 200
+    0x7301, // V3 += 1
+    0x1200, // jump 200
+];
+
+fn machine() -> Chip8 {
+    let mut chip8 = common::load(Chip8::new(), PROGRAM);
+    chip8.seed(3);
+    chip8.rewind = Some(Rewind::new(8, 16));
+    chip8
+}
+
+#[test]
+fn step_back_restores_the_earlier_state() {
+    let mut chip8 = machine();
+    // The state each instruction started from, with the host's keypad and
+    // timers as they were when it ran.
+    let mut states = Vec::new();
+    for frame in 0..20 {
+        chip8.keypad[5] = frame % 3 == 0;
+        for _ in 0..7 {
+            states.push(chip8.save_state());
+            chip8.cycle().unwrap();
+        }
+        chip8.tick_timers();
+    }
+
+    // Each step goes further back from where the last one landed.
+    let mut back = 0;
+    for step in [1, 5, 1, 23, 30] {
+        assert_eq!(chip8.step_back(step), step);
+        back += step;
+        assert_eq!(chip8.save_state(), states[states.len() - back], "{} back", back);
+    }
+}
+
+#[test]
+fn step_back_stops_at_the_oldest_snapshot() {
+    let mut chip8 = machine();
+    for _ in 0..500 {
+        chip8.cycle().unwrap();
+    }
+
+    let available = chip8.rewind.as_ref().unwrap().len();
+    assert!(available < 500);
+    assert_eq!(chip8.step_back(1000), available);
+    assert_eq!(chip8.cycles, 500 - available);
+    assert_eq!(chip8.step_back(1), 0);
+}
+
+#[test]
+fn step_back_does_nothing_without_rewind() {
+    let mut chip8 = machine();
+    chip8.rewind = None;
+    chip8.cycle().unwrap();
+    assert_eq!(chip8.step_back(1), 0);
+    assert_eq!(chip8.pc, APP_START + 2);
+}
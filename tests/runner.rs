@@ -0,0 +1,65 @@
+//! Headless runs: how they end and how many frames they report.
+
+mod common;
+
+use chip8::runner::{run, Finish, Until, DEFAULT_FRAMES};
+use chip8::{Chip8, Platform, Quirks};
+
+use common::load;
+
+fn machine(program: &[u16]) -> Chip8 {
+    load(Chip8::new(), program)
+}
+
+#[test]
+fn a_condition_that_already_holds_runs_no_frames() {
+    let mut chip8 = machine(&[0x1200]);
+    let report = run(&mut chip8, 10, Some(5), &[Until::SelfJump]).unwrap();
+    assert_eq!(report.finish, Finish::SelfJump(0x200));
+    assert_eq!(report.frames, 0);
+    assert_eq!(chip8.cycles, 0);
+}
+
+#[test]
+fn frames_count_the_partly_run_last_one() {
+    // Ten instructions per frame; the jump is reached after 25.
+    let mut program = vec![0x7001; 25];
+    program.push(0x1232);
+    let mut chip8 = machine(&program);
+
+    let report = run(&mut chip8, 10, None, &[Until::SelfJump]).unwrap();
+    assert_eq!(report.finish, Finish::SelfJump(0x232));
+    assert_eq!((report.frames, chip8.cycles), (3, 25));
+}
+
+#[test]
+fn a_condition_met_on_a_frame_boundary_ends_before_the_next_frame() {
+    let mut program = vec![0x7001; 20];
+    program.push(0x1228);
+    let mut chip8 = machine(&program);
+
+    let report = run(&mut chip8, 10, None, &[Until::Pc(0x228)]).unwrap();
+    assert_eq!((report.finish, report.frames), (Finish::Pc(0x228), 2));
+
+    // Also when it is the last frame allowed.
+    let mut chip8 = machine(&program);
+    let report = run(&mut chip8, 10, Some(2), &[Until::Pc(0x228)]).unwrap();
+    assert_eq!((report.finish, report.frames), (Finish::Pc(0x228), 2));
+}
+
+#[test]
+fn runs_without_a_limit_stop_at_the_default() {
+    let mut chip8 = machine(&[0x7001, 0x1200]);
+    let report = run(&mut chip8, 10, None, &[Until::Memory { address: 0, value: 0xAA }]).unwrap();
+    assert_eq!((report.finish, report.frames), (Finish::Frames, DEFAULT_FRAMES));
+    assert_eq!(chip8.cycles, DEFAULT_FRAMES * 10);
+}
+
+#[test]
+fn halting_ends_the_run() {
+    let schip = Chip8::with_platform(Platform::SuperChip, Quirks::schip());
+    let mut chip8 = load(schip, &[0x6005, 0x00FD, 0x1200]);
+    let report = run(&mut chip8, 10, Some(5), &[]).unwrap();
+    assert_eq!((report.finish, report.frames), (Finish::Halted, 1));
+    assert_eq!(chip8.registers[0], 5);
+}
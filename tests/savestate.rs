@@ -0,0 +1,81 @@
+//! Save states: a round trip restores the machine exactly, and damaged or
+//! foreign states are turned away without touching it.
+
+mod common;
+
+use chip8::savestate::VERSION;
+use chip8::Chip8;
+
+use common::error;
+
+/// Draws random digits across the screen while the delay timer runs.
+const PROGRAM: &[u16] = &[
+    0x6005, // V0 = 5
+    0xF015, // delay = V0
+    0xC1FF, // V1 = random
+    0xF029, // I = glyph for V0
+    0xD015, // draw at (V0, V1)
+    0x7001, // V0 += 1
+    0x1202, // jump 202
+];
+
+fn machine() -> Chip8 {
+    let mut chip8 = common::load(Chip8::new(), PROGRAM);
+    chip8.seed(7);
+    chip8
+}
+
+fn run(chip8: &mut Chip8, frames: usize) {
+    for _ in 0..frames {
+        chip8.run_frame(10).unwrap();
+    }
+}
+
+#[test]
+fn round_trip_restores_the_machine() {
+    let mut chip8 = machine();
+    run(&mut chip8, 5);
+    chip8.keypad[3] = true;
+    let state = chip8.save_state();
+
+    let mut restored = Chip8::new();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+    assert_eq!(restored.screen, chip8.screen);
+    assert_eq!(restored.rng, chip8.rng);
+
+    // Including the random number generator, so both carry on identically.
+    run(&mut chip8, 5);
+    run(&mut restored, 5);
+    assert_eq!(restored.save_state(), chip8.save_state());
+}
+
+#[test]
+fn rejects_a_bad_checksum() {
+    let mut state = machine().save_state();
+    state[0x300] ^= 0x01;
+
+    let mut chip8 = Chip8::new();
+    let before = chip8.save_state();
+    assert_eq!(error(chip8.load_state(&state)), "bad save state: checksum mismatch");
+    assert_eq!(chip8.save_state(), before);
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut state = machine().save_state();
+    state[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+
+    let mut chip8 = Chip8::new();
+    let before = chip8.save_state();
+    let error = error(chip8.load_state(&state));
+    assert!(error.starts_with(&format!("bad save state: version {} is not supported", VERSION + 1)), "{}", error);
+    assert_eq!(chip8.save_state(), before);
+}
+
+#[test]
+fn rejects_other_files() {
+    let mut chip8 = Chip8::new();
+    assert_eq!(error(chip8.load_state(b"C8")), "bad save state: not a save state");
+    assert_eq!(error(chip8.load_state(b"PNG\0 not a save state")), "bad save state: not a save state");
+}